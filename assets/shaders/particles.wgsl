@@ -0,0 +1,43 @@
+#import bevy_sprite::mesh2d_view_bindings::view
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) offset: vec2<f32>,
+    @location(1) colour: vec4<f32>,
+};
+
+// xyz is the particle position, w its radius.
+@vertex
+fn vertex(
+    @builtin(vertex_index) index: u32,
+    @location(0) position_radius: vec4<f32>,
+    @location(1) colour: vec4<f32>,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let offset = corners[index];
+    let position = position_radius.xyz + vec3<f32>(offset * position_radius.w, 0.0);
+
+    var out: VertexOutput;
+    out.clip_position = view.clip_from_world * vec4<f32>(position, 1.0);
+    out.offset = offset;
+    out.colour = colour;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.offset);
+    let edge = fwidth(distance);
+    let coverage = 1.0 - smoothstep(1.0 - edge, 1.0, distance);
+    if coverage <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.colour.rgb, in.colour.a * coverage);
+}
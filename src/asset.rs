@@ -3,7 +3,12 @@ use bevy:: {
     sprite::Mesh2dHandle
 };
 
-use crate::collider::{BORDER_DISTANCE, BORDER_THICKNESS};
+use bevy::utils::HashMap;
+
+use crate::{
+    collider::{BORDER_DISTANCE, BORDER_THICKNESS},
+    colormap::{ColourMap, COLOUR_MAPS},
+};
 
 pub struct AssetPlugin;
 
@@ -13,29 +18,70 @@ impl Plugin for AssetPlugin {
     }
 }
 
-#[derive(Resource)]
-pub struct ProbeAssets {
-    pub triangle: Mesh2dHandle,
-    pub blue: Handle<ColorMaterial>,
-}
-
 #[derive(Resource)]
 pub struct ParticleAssets {
     pub circle: Mesh2dHandle,
-    pub red: Handle<ColorMaterial>,
-    pub blue: Handle<ColorMaterial>
+    pub palette: Vec<Handle<ColorMaterial>>,
+    pub gradients: HashMap<ColourMap, Vec<Handle<ColorMaterial>>>,
+}
+
+impl ParticleAssets {
+    pub fn material(&self, species: usize, charge: f32) -> Handle<ColorMaterial> {
+        self.faded(species, charge, 1.0)
+    }
+
+    pub fn faded(&self, species: usize, charge: f32, alpha: f32) -> Handle<ColorMaterial> {
+        let sign = if charge > 0.0 { 0 } else if charge < 0.0 { 1 } else { 2 };
+        let step = ((alpha.clamp(0.0, 1.0) * FADE_STEPS as f32).ceil() as usize).clamp(1, FADE_STEPS) - 1;
+        self.palette[((species % PALETTE_SPECIES) * 3 + sign) * FADE_STEPS + step].clone()
+    }
+
+    pub fn gradient(&self, map: ColourMap, t: f32) -> Handle<ColorMaterial> {
+        let steps = &self.gradients[&map];
+        let step = (t.clamp(0.0, 1.0) * (steps.len() - 1) as f32).round() as usize;
+        steps[step].clone()
+    }
+}
+
+#[cfg(test)]
+impl ParticleAssets {
+    // Handles for tests that spawn particles without a renderer. Palette
+    // entries are told apart by their ids.
+    pub fn placeholder() -> ParticleAssets {
+        ParticleAssets {
+            circle: Mesh2dHandle(Handle::default()),
+            palette: (0..PALETTE_SPECIES * 3 * FADE_STEPS)
+                .map(|i| Handle::weak_from_u128(i as u128 + 1))
+                .collect(),
+            gradients: Default::default(),
+        }
+    }
 }
 
+const PALETTE_SPECIES: usize = 8;
+const FADE_STEPS: usize = 8;
+const GRADIENT_STEPS: usize = 32;
+const GOLDEN_ANGLE: f32 = 137.508;
+
 #[derive(Resource)]
 pub struct ForcefieldAssets {
     pub triangle: Mesh2dHandle,
+    pub square: Mesh2dHandle,
     pub green: Handle<ColorMaterial>
 }
 
+#[derive(Resource)]
+pub struct SinkAssets {
+    pub circle: Mesh2dHandle,
+    pub square: Mesh2dHandle,
+    pub black: Handle<ColorMaterial>
+}
+
 #[derive(Resource)]
 pub struct ColliderAssets {
     pub h_rectangle: Mesh2dHandle,
     pub v_rectangle: Mesh2dHandle,
+    pub square: Mesh2dHandle,
     pub white: Handle<ColorMaterial>
 }
 
@@ -44,23 +90,40 @@ fn generate_assets(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    commands.insert_resource(
-        ProbeAssets {
-            triangle: Mesh2dHandle(meshes.add(
-                                Triangle2d::new(
-                                    Vec2::X * 250.0,
-                                    Vec2::new(-100.0, -100.0),
-                                    Vec2::new(-100.0, 100.0)
-                                ))),
-            blue: materials.add(Color::srgba(0.2, 0.2, 1.0, 0.25)),
+    // Each species gets a hue spread around the colour wheel by the golden
+    // angle, with a positive, negative and neutral variant at a few levels of
+    // transparency. Species 0 keeps the old red for positive and blue for
+    // negative charges.
+    let mut palette = Vec::new();
+    for species in 0..PALETTE_SPECIES {
+        let hue = species as f32 * GOLDEN_ANGLE;
+        for colour in [
+            Color::hsl(hue % 360.0, 1.0, 0.5),
+            Color::hsl((hue + 240.0) % 360.0, 1.0, 0.5),
+            Color::hsl(hue % 360.0, 0.2, 0.7),
+        ] {
+            for step in 1..=FADE_STEPS {
+                let alpha = step as f32 / FADE_STEPS as f32;
+                palette.push(materials.add(colour.with_alpha(alpha)));
+            }
         }
-    );
+    }
+
+    let gradients = COLOUR_MAPS
+        .iter()
+        .map(|map| {
+            let steps = (0..GRADIENT_STEPS)
+                .map(|step| materials.add(map.sample(step as f32 / (GRADIENT_STEPS - 1) as f32)))
+                .collect();
+            (*map, steps)
+        })
+        .collect();
 
     commands.insert_resource(
         ParticleAssets {
             circle: Mesh2dHandle(meshes.add(Circle { radius: 10.0 })),
-            red: materials.add(Color::srgb(1.0, 0.0, 0.0)),
-            blue: materials.add(Color::srgb(0.0, 0.0, 1.0)),
+            palette,
+            gradients,
         }
     );
 
@@ -72,6 +135,7 @@ fn generate_assets(
                                     Vec2::new(-250.0, -250.0),
                                     Vec2::new(-250.0, 250.0)
                                 ))),
+            square: Mesh2dHandle(meshes.add(Rectangle::new(1.0, 1.0))),
             green: materials.add(Color::srgba(0.0, 1.0, 0.0, 0.25)),
         }
     );
@@ -80,7 +144,16 @@ fn generate_assets(
         ColliderAssets {
             h_rectangle: Mesh2dHandle(meshes.add(Rectangle::new(2.0 * BORDER_DISTANCE, BORDER_THICKNESS))),
             v_rectangle: Mesh2dHandle(meshes.add(Rectangle::new(BORDER_THICKNESS, 2.0 * BORDER_DISTANCE))),
+            square: Mesh2dHandle(meshes.add(Rectangle::new(1.0, 1.0))),
             white: materials.add(Color::srgb(1.0, 1.0, 1.0))
         }
     );
+
+    commands.insert_resource(
+        SinkAssets {
+            circle: Mesh2dHandle(meshes.add(Circle { radius: 1.0 })),
+            square: Mesh2dHandle(meshes.add(Rectangle::new(1.0, 1.0))),
+            black: materials.add(Color::srgba(0.1, 0.1, 0.1, 0.8)),
+        }
+    );
 }
@@ -0,0 +1,212 @@
+use bevy::{
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::{
+    particle::*,
+    reaction::BondEvent,
+};
+
+pub struct BondPlugin;
+
+impl Plugin for BondPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BondSettings {
+            stiffness: BOND_STIFFNESS,
+            damping: BOND_DAMPING,
+            break_stretch: BOND_BREAK_STRETCH,
+        });
+        app.add_systems(Update, (create_bonds, apply_bond_forces, draw_bonds).chain());
+    }
+}
+
+// A spring between two particles. It breaks once it is stretched past
+// `break_length` or either end is despawned.
+#[derive(Component)]
+pub struct Bond {
+    pub a: Entity,
+    pub b: Entity,
+    pub rest_length: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub break_length: f32,
+}
+
+// Used for bonds created from `BondEvent`s, which take their current
+// distance as rest length and break at `break_stretch` times that.
+#[derive(Resource)]
+pub struct BondSettings {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub break_stretch: f32,
+}
+
+const BOND_STIFFNESS: f32 = 50.0;
+const BOND_DAMPING: f32 = 2.0;
+const BOND_BREAK_STRETCH: f32 = 3.0;
+const MIN_REST_LENGTH: f32 = 20.0;
+
+fn create_bonds(
+    mut commands: Commands,
+    mut events: EventReader<BondEvent>,
+    settings: Res<BondSettings>,
+    q_bonds: Query<&Bond>,
+    q_particles: Query<&Transform, With<Particle>>,
+) {
+    let mut bonded = q_bonds
+        .iter()
+        .map(|bond| (bond.a.min(bond.b), bond.a.max(bond.b)))
+        .collect::<HashSet<_>>();
+
+    for event in events.read() {
+        if event.a == event.b || !bonded.insert((event.a.min(event.b), event.a.max(event.b))) {
+            continue;
+        }
+        let Ok([transform_a, transform_b]) = q_particles.get_many([event.a, event.b]) else {
+            continue;
+        };
+        let rest_length = transform_a.translation.distance(transform_b.translation).max(MIN_REST_LENGTH);
+        commands.spawn(Bond {
+            a: event.a,
+            b: event.b,
+            rest_length,
+            stiffness: settings.stiffness,
+            damping: settings.damping,
+            break_length: rest_length * settings.break_stretch,
+        });
+    }
+}
+
+fn apply_bond_forces(
+    mut commands: Commands,
+    time: Res<Time>,
+    q_bonds: Query<(Entity, &Bond)>,
+    mut q_particles: Query<(&mut Velocity, &Transform, &Cancelled)>,
+) {
+    for (entity, bond) in q_bonds.iter() {
+        let Ok([(mut velocity_a, transform_a, cancelled_a), (mut velocity_b, transform_b, cancelled_b)])
+            = q_particles.get_many_mut([bond.a, bond.b])
+        else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let delta = (transform_b.translation - transform_a.translation).xy();
+        let distance = delta.length();
+        if cancelled_a.0 || cancelled_b.0 || distance > bond.break_length {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let direction = delta.normalize_or_zero();
+        let stretch = distance - bond.rest_length;
+        let closing_speed = (velocity_b.0 - velocity_a.0).dot(direction);
+        let force = direction * (bond.stiffness * stretch + bond.damping * closing_speed);
+        velocity_a.0 += force * time.delta_seconds();
+        velocity_b.0 -= force * time.delta_seconds();
+    }
+}
+
+fn draw_bonds(
+    mut gizmos: Gizmos,
+    q_bonds: Query<&Bond>,
+    q_particles: Query<&Transform, With<Particle>>,
+) {
+    for bond in q_bonds.iter() {
+        let Ok([transform_a, transform_b]) = q_particles.get_many([bond.a, bond.b]) else {
+            continue;
+        };
+        let a = transform_a.translation.xy();
+        let b = transform_b.translation.xy();
+        // Fade from white at rest length to red close to breaking.
+        let strain = ((a.distance(b) - bond.rest_length) / (bond.break_length - bond.rest_length)).clamp(0.0, 1.0);
+        gizmos.line_2d(a, b, Color::srgb(1.0, 1.0 - strain, 1.0 - strain));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_millis(10));
+        world.insert_resource(time);
+        world.insert_resource(BondSettings {
+            stiffness: BOND_STIFFNESS,
+            damping: BOND_DAMPING,
+            break_stretch: 2.0,
+        });
+        world.init_resource::<Events<BondEvent>>();
+        world
+    }
+
+    fn spawn(world: &mut World, x: f32) -> Entity {
+        spawn_test_particle(world, Vec2::new(x, 0.0), Vec2::ZERO, 0, 0.0)
+    }
+
+    fn run(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((create_bonds, apply_bond_forces).chain());
+        schedule.run(world);
+        world.resource_mut::<Events<BondEvent>>().clear();
+    }
+
+    fn bond_count(world: &mut World) -> usize {
+        world.query::<&Bond>().iter(world).count()
+    }
+
+    #[test]
+    fn bond_events_create_one_spring_per_pair() {
+        let mut world = world();
+        let a = spawn(&mut world, 0.0);
+        let b = spawn(&mut world, 50.0);
+        world.send_event(BondEvent { a, b });
+        world.send_event(BondEvent { a: b, b: a });
+        run(&mut world);
+
+        let bond = world.query::<&Bond>().single(&world);
+        assert_eq!(bond.rest_length, 50.0);
+        assert_eq!(bond.break_length, 100.0);
+        // Stretched springs pull their ends together.
+        world.get_mut::<Transform>(b).unwrap().translation.x = 60.0;
+        run(&mut world);
+        assert!(world.get::<Velocity>(a).unwrap().0.x > 0.0);
+        assert!(world.get::<Velocity>(b).unwrap().0.x < 0.0);
+    }
+
+    #[test]
+    fn bonds_break_past_their_break_length() {
+        let mut world = world();
+        let a = spawn(&mut world, 0.0);
+        let b = spawn(&mut world, 50.0);
+        world.send_event(BondEvent { a, b });
+        run(&mut world);
+        assert_eq!(bond_count(&mut world), 1);
+
+        world.get_mut::<Transform>(b).unwrap().translation.x = 99.0;
+        run(&mut world);
+        assert_eq!(bond_count(&mut world), 1);
+
+        world.get_mut::<Transform>(b).unwrap().translation.x = 101.0;
+        run(&mut world);
+        assert_eq!(bond_count(&mut world), 0);
+    }
+
+    #[test]
+    fn bonds_break_when_an_end_goes_away() {
+        let mut world = world();
+        let a = spawn(&mut world, 0.0);
+        let b = spawn(&mut world, 50.0);
+        world.send_event(BondEvent { a, b });
+        run(&mut world);
+
+        world.despawn(a);
+        run(&mut world);
+        assert_eq!(bond_count(&mut world), 0);
+    }
+}
@@ -4,7 +4,11 @@ use bevy::{
     math::bounding::Aabb2d,
 };
 
-use crate::asset::ColliderAssets;
+use crate:: {
+    asset::ColliderAssets,
+    particle::Velocity,
+    physics::{SimulationConfig, BoundaryMode},
+};
 
 pub struct ColliderPlugin {
     pub parallel: bool
@@ -13,6 +17,7 @@ pub struct ColliderPlugin {
 impl Plugin for ColliderPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_colliders);
+        app.add_systems(Update, toggle_walls);
         if self.parallel {
             app.add_systems(Update, handle_collisions_parallel);
         } else {
@@ -25,10 +30,18 @@ pub const BORDER_DISTANCE: f32 = 5000.0;
 pub const BORDER_THICKNESS: f32 = 100.0;
 
 #[derive(Component)]
-struct Collider {
-    aabb: Aabb2d
+pub struct Collider {
+    pub aabb: Aabb2d
 }
 
+// The arena walls, which only collide in `BoundaryMode::Reflect`.
+#[derive(Component)]
+pub struct Wall;
+
+// Colliders that are ignored, but kept around to be enabled again.
+#[derive(Component)]
+pub struct Disabled;
+
 fn spawn_colliders(
     mut commands: Commands,
     assets: Res<ColliderAssets>,
@@ -45,7 +58,8 @@ fn spawn_colliders(
             material: assets.white.clone(),
             transform: Transform::from_xyz(0.0, -BORDER_DISTANCE - BORDER_THICKNESS/2.0, 0.0), 
             ..default()
-        }
+        },
+        Wall
     ));
     
     commands.spawn((
@@ -60,14 +74,15 @@ fn spawn_colliders(
             material: assets.white.clone(),
             transform: Transform::from_xyz(0.0, BORDER_DISTANCE + BORDER_THICKNESS/2.0, 0.0), 
             ..default()
-        }
+        },
+        Wall
     ));
 
     commands.spawn((
         Collider {
             aabb: Aabb2d {
-                min: Vec2::new(-BORDER_DISTANCE, -BORDER_DISTANCE),
-                max: Vec2::new(BORDER_DISTANCE, -BORDER_DISTANCE - BORDER_THICKNESS)
+                min: Vec2::new(-BORDER_DISTANCE - BORDER_THICKNESS, -BORDER_DISTANCE),
+                max: Vec2::new(-BORDER_DISTANCE, BORDER_DISTANCE)
             }
         },
         MaterialMesh2dBundle {
@@ -75,14 +90,15 @@ fn spawn_colliders(
             material: assets.white.clone(),
             transform: Transform::from_xyz(-BORDER_DISTANCE - BORDER_THICKNESS/2.0, 0.0, 0.0),
             ..default()
-        }
+        },
+        Wall
     ));
  
     commands.spawn((
         Collider {
             aabb: Aabb2d {
-                min: Vec2::new(-BORDER_DISTANCE, BORDER_DISTANCE),
-                max: Vec2::new(BORDER_DISTANCE, BORDER_DISTANCE + BORDER_THICKNESS)
+                min: Vec2::new(BORDER_DISTANCE, -BORDER_DISTANCE),
+                max: Vec2::new(BORDER_DISTANCE + BORDER_THICKNESS, BORDER_DISTANCE)
             }
         },
         MaterialMesh2dBundle {
@@ -90,28 +106,125 @@ fn spawn_colliders(
             material: assets.white.clone(),
             transform: Transform::from_xyz(BORDER_DISTANCE + BORDER_THICKNESS/2.0, 0.0, 0.0), 
             ..default()
-        }
+        },
+        Wall
     ));
 }
 
-
-
-fn handle_collisions_parallel(
-    
+fn toggle_walls(
+    mut commands: Commands,
+    config: Res<SimulationConfig>,
+    mut q: Query<(Entity, &mut Visibility), With<Wall>>,
 ) {
-
+    if !config.is_changed() {
+        return;
+    }
+    for (entity, mut visibility) in q.iter_mut() {
+        if config.boundary == BoundaryMode::Reflect {
+            commands.entity(entity).remove::<Disabled>();
+            *visibility = Visibility::Inherited;
+        } else {
+            commands.entity(entity).insert(Disabled);
+            *visibility = Visibility::Hidden;
+        }
+    }
 }
 
-fn handle_collisions_single_threaded(
-
+pub(crate) fn handle_collisions_parallel(
+    q_colliders: Query<&Collider, Without<Disabled>>,
+    mut q_particles: Query<(&mut Velocity, &Transform)>,
 ) {
+    q_particles.par_iter_mut().for_each(|(mut velocity, transform)| {
+        for collider in q_colliders.iter() {
+            velocity.0 = calculate_collision_velocity(
+                collider.aabb,
+                transform.translation.xy(),
+                velocity.0
+            );
+        }
+    });
+}
 
+pub(crate) fn handle_collisions_single_threaded(
+    q_colliders: Query<&Collider, Without<Disabled>>,
+    mut q_particles: Query<(&mut Velocity, &Transform)>,
+) {
+    for collider in q_colliders.iter() {
+        for (mut velocity, transform) in q_particles.iter_mut() {
+            velocity.0 = calculate_collision_velocity(
+                collider.aabb,
+                transform.translation.xy(),
+                velocity.0
+            );
+        }
+    }
 }
 
+// Particles inside a collider get their velocity pointed out of the
+// nearest face, so they leave the collider the way they came in.
 fn calculate_collision_velocity(
     aabb: Aabb2d,
     position: Vec2,
     velocity: Vec2
 ) -> Vec2 {
-    Vec2::ZERO
+    if position.cmplt(aabb.min).any() || position.cmpgt(aabb.max).any() {
+        return velocity;
+    }
+    let to_min = position - aabb.min;
+    let to_max = aabb.max - position;
+    let nearest = to_min.min(to_max).min_element();
+    if nearest == to_min.x {
+        Vec2::new(-velocity.x.abs(), velocity.y)
+    } else if nearest == to_max.x {
+        Vec2::new(velocity.x.abs(), velocity.y)
+    } else if nearest == to_min.y {
+        Vec2::new(velocity.x, -velocity.y.abs())
+    } else {
+        Vec2::new(velocity.x, velocity.y.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: Aabb2d = Aabb2d {
+        min: Vec2::new(0.0, 0.0),
+        max: Vec2::new(100.0, 20.0),
+    };
+
+    #[test]
+    fn particles_are_turned_out_of_the_nearest_face() {
+        let velocity = Vec2::new(3.0, -4.0);
+        assert_eq!(calculate_collision_velocity(BOX, Vec2::new(2.0, 10.0), velocity), Vec2::new(-3.0, -4.0));
+        assert_eq!(calculate_collision_velocity(BOX, Vec2::new(98.0, 10.0), velocity), Vec2::new(3.0, -4.0));
+        assert_eq!(calculate_collision_velocity(BOX, Vec2::new(50.0, 3.0), velocity), Vec2::new(3.0, -4.0));
+        assert_eq!(calculate_collision_velocity(BOX, Vec2::new(50.0, 17.0), velocity), Vec2::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn particles_outside_keep_their_velocity() {
+        let velocity = Vec2::new(3.0, -4.0);
+        for position in [Vec2::new(-1.0, 10.0), Vec2::new(101.0, 10.0), Vec2::new(50.0, -1.0), Vec2::new(50.0, 21.0)] {
+            assert_eq!(calculate_collision_velocity(BOX, position, velocity), velocity);
+        }
+    }
+
+    #[test]
+    fn disabled_colliders_are_ignored() {
+        let mut world = World::new();
+        world.spawn(Collider { aabb: BOX });
+        let disabled = world.spawn((Collider { aabb: Aabb2d::new(Vec2::new(50.0, 110.0), Vec2::new(50.0, 10.0)) }, Disabled)).id();
+        let enabled = world.spawn((Velocity(Vec2::new(3.0, 4.0)), Transform::from_xyz(2.0, 10.0, 0.0))).id();
+        let ignored = world.spawn((Velocity(Vec2::new(3.0, 4.0)), Transform::from_xyz(2.0, 110.0, 0.0))).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(handle_collisions_single_threaded);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Velocity>(enabled).unwrap().0, Vec2::new(-3.0, 4.0));
+        assert_eq!(world.get::<Velocity>(ignored).unwrap().0, Vec2::new(3.0, 4.0));
+        world.entity_mut(disabled).remove::<Disabled>();
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Velocity>(ignored).unwrap().0, Vec2::new(-3.0, 4.0));
+    }
 }
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ColourMap {
+    Viridis,
+    Magma,
+    Diverging,
+}
+
+pub const COLOUR_MAPS: [ColourMap; 3] = [ColourMap::Viridis, ColourMap::Magma, ColourMap::Diverging];
+
+// A handful of stops from the matplotlib maps, interpolated linearly.
+const VIRIDIS: [[f32; 3]; 6] = [
+    [0.267, 0.005, 0.329],
+    [0.254, 0.265, 0.530],
+    [0.164, 0.471, 0.558],
+    [0.135, 0.659, 0.518],
+    [0.478, 0.821, 0.319],
+    [0.993, 0.906, 0.144],
+];
+
+const MAGMA: [[f32; 3]; 6] = [
+    [0.001, 0.000, 0.014],
+    [0.232, 0.060, 0.437],
+    [0.550, 0.161, 0.506],
+    [0.868, 0.288, 0.409],
+    [0.994, 0.624, 0.427],
+    [0.987, 0.991, 0.750],
+];
+
+const DIVERGING: [[f32; 3]; 5] = [
+    [0.230, 0.299, 0.754],
+    [0.552, 0.690, 0.996],
+    [0.865, 0.865, 0.865],
+    [0.958, 0.604, 0.484],
+    [0.706, 0.016, 0.150],
+];
+
+impl ColourMap {
+    fn stops(&self) -> &'static [[f32; 3]] {
+        match self {
+            ColourMap::Viridis => &VIRIDIS,
+            ColourMap::Magma => &MAGMA,
+            ColourMap::Diverging => &DIVERGING,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColourMap::Viridis => "viridis",
+            ColourMap::Magma => "magma",
+            ColourMap::Diverging => "diverging",
+        }
+    }
+
+    pub fn next(&self) -> ColourMap {
+        match self {
+            ColourMap::Viridis => ColourMap::Magma,
+            ColourMap::Magma => ColourMap::Diverging,
+            ColourMap::Diverging => ColourMap::Viridis,
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let stops = self.stops();
+        let x = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (x.floor() as usize).min(stops.len() - 2);
+        let f = x - i as f32;
+        let [r0, g0, b0] = stops[i];
+        let [r1, g1, b1] = stops[i + 1];
+        Color::srgb(r0 + (r1 - r0) * f, g0 + (g1 - g0) * f, b0 + (b1 - b0) * f)
+    }
+}
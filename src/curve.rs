@@ -0,0 +1,43 @@
+#[derive(Clone)]
+pub struct Curve {
+    keyframes: Vec<(f32, f32)>,
+}
+
+impl Curve {
+    pub fn new(mut keyframes: Vec<(f32, f32)>) -> Self {
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Curve { keyframes }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |(t, _)| *t)
+    }
+
+    // Linear interpolation between keyframes, holding the end values
+    // outside of the keyed range.
+    pub fn sample(&self, t: f32) -> f32 {
+        let Some(first) = self.keyframes.first() else {
+            return 0.0;
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.keyframes.windows(2) {
+            let (t0, v0) = pair[0];
+            let (t1, v1) = pair[1];
+            if t <= t1 {
+                let f = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                return v0 + (v1 - v0) * f;
+            }
+        }
+        self.keyframes[self.keyframes.len() - 1].1
+    }
+
+    pub fn sample_looped(&self, t: f32) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return self.sample(t);
+        }
+        self.sample(t.rem_euclid(duration))
+    }
+}
@@ -1,106 +1,603 @@
 use bevy:: {
     prelude::*,
-    sprite::MaterialMesh2dBundle,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
 };
 
-use std::f32::consts::PI;
+use bevy::utils::HashMap;
 
 use crate:: {
-    asset::ProbeAssets,
     particle::*,
+    asset::ParticleAssets,
+    collider::BORDER_DISTANCE,
+    colormap::ColourMap,
+    lifetime::{Lifetime, age_particles},
+    trail::TrailPlugin,
+    render::ParticleRenderPlugin,
 };
 
 pub struct DisplayPlugin;
 
 impl Plugin for DisplayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_probes);
+        app.add_plugins(TrailPlugin);
+        app.add_plugins(ParticleRenderPlugin);
+        app.insert_resource(DensityGrid {
+            resolution: DENSITY_RESOLUTION,
+            max_density: DENSITY_SCALE,
+            colour_map: ColourMap::Magma,
+            visible: true,
+            counts: Vec::new(),
+        });
+        app.insert_resource(VelocityGrid {
+            resolution: VELOCITY_RESOLUTION,
+            display: VelocityDisplay::Arrows,
+            arrow_scale: ARROW_SCALE,
+            max_speed: VELOCITY_SCALE,
+            colour_map: ColourMap::Viridis,
+            averages: Vec::new(),
+        });
+        app.add_systems(Startup, spawn_density_overlay);
         app.add_systems(Update, (
-                update_density_probes_count,
-                update_velocity_probes_count)
+                update_density_grid,
+                update_velocity_grid)
             );
-        app.add_systems(Update, update_velocity_probes_visuals);
+        app.add_systems(Update, update_density_overlay.after(update_density_grid));
+        app.add_systems(Update, draw_velocity_grid.after(update_velocity_grid));
+        app.insert_resource(ColourMode {
+            quantity: ColourQuantity::Species,
+            map: ColourMap::Viridis,
+            min: 0.0,
+            max: 0.0,
+        });
+        app.add_systems(Update, colour_particles.after(update_density_grid).after(age_particles));
     }
 }
 
-#[derive(Component)]
-struct DensityProbe {
-    radius: f32,
-    count: u32
+// Particle counts binned on a `resolution` x `resolution` grid over the
+// arena, recounted every frame. The overlay maps `max_density` particles per
+// cell to the top of the colour map.
+#[derive(Resource)]
+pub struct DensityGrid {
+    pub resolution: u32,
+    pub max_density: f32,
+    pub colour_map: ColourMap,
+    pub visible: bool,
+    counts: Vec<u32>,
+}
+
+impl DensityGrid {
+    fn cell(&self, position: Vec2) -> Option<usize> {
+        let uv = (position + BORDER_DISTANCE) / (2.0 * BORDER_DISTANCE);
+        if uv.cmplt(Vec2::ZERO).any() || uv.cmpge(Vec2::ONE).any() {
+            return None;
+        }
+        let x = (uv.x * self.resolution as f32) as usize;
+        let y = (uv.y * self.resolution as f32) as usize;
+        Some(y * self.resolution as usize + x)
+    }
+
+    pub fn density_at(&self, position: Vec2) -> u32 {
+        self.cell(position)
+            .and_then(|cell| self.counts.get(cell).copied())
+            .unwrap_or(0)
+    }
 }
 
 #[derive(Component)]
-struct VelocityProbe {
-    radius: f32,
-    velocity: Vec2
+struct DensityOverlay;
+
+const DENSITY_RESOLUTION: u32 = 64;
+const DENSITY_SCALE: f32 = 10.0;
+const DENSITY_ALPHA: f32 = 0.6;
+const VELOCITY_RESOLUTION: u32 = 20;
+const VELOCITY_SCALE: f32 = 500.0;
+const ARROW_SCALE: f32 = 1.0;
+const STREAMLINE_STRIDE: usize = 2;
+const STREAMLINE_STEPS: usize = 64;
+const MIN_STREAMLINE_SPEED: f32 = 1.0;
+const CLUSTER_DISTANCE: f32 = 50.0;
+const GOLDEN_RATIO: f32 = 0.618_034;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColourQuantity {
+    Species,
+    Speed,
+    KineticEnergy,
+    Density,
+    Age,
+    Cluster,
+    Acceleration,
 }
 
-fn spawn_probes(
-    mut commands: Commands,
-    assets: Res<ProbeAssets>,
-) {
-    for x in -8..8 {
-        for y in -8..8 {
-            commands.spawn((
-                VelocityProbe {
-                    radius: 250.0,
-                    velocity: Vec2::ZERO
-                },
-                MaterialMesh2dBundle {
-                    mesh: assets.triangle.clone(),
-                    material: assets.blue.clone(),
-                    transform: Transform::from_xyz(
-                        500.0 * x as f32,
-                        500.0 * y as f32,
-                        0.0),
-                    ..default()
-                }
-            ));
+impl ColourQuantity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColourQuantity::Species => "charge / species",
+            ColourQuantity::Speed => "speed",
+            ColourQuantity::KineticEnergy => "kinetic energy",
+            ColourQuantity::Density => "local density",
+            ColourQuantity::Age => "age",
+            ColourQuantity::Cluster => "cluster",
+            ColourQuantity::Acceleration => "acceleration (dv/dt)",
+        }
+    }
+
+    pub fn next(&self) -> ColourQuantity {
+        match self {
+            ColourQuantity::Species => ColourQuantity::Speed,
+            ColourQuantity::Speed => ColourQuantity::KineticEnergy,
+            ColourQuantity::KineticEnergy => ColourQuantity::Density,
+            ColourQuantity::Density => ColourQuantity::Age,
+            ColourQuantity::Age => ColourQuantity::Cluster,
+            ColourQuantity::Cluster => ColourQuantity::Acceleration,
+            ColourQuantity::Acceleration => ColourQuantity::Species,
+        }
+    }
+}
+
+// How particles are coloured. Everything but `Species` maps the quantity
+// from `min` to `max`, which follow the values of the current frame, onto
+// `map`. Clusters get scattered colours by id instead.
+#[derive(Resource)]
+pub struct ColourMode {
+    pub quantity: ColourQuantity,
+    pub map: ColourMap,
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VelocityDisplay {
+    Off,
+    Arrows,
+    Streamlines,
+}
+
+// Mean particle velocity per cell of a `resolution` x `resolution` grid over
+// the arena. Empty cells average to zero. Arrows are `arrow_scale` world
+// units long per unit of speed, capped at a cell, and both arrows and
+// streamlines are coloured up to `max_speed`.
+#[derive(Resource)]
+pub struct VelocityGrid {
+    pub resolution: u32,
+    pub display: VelocityDisplay,
+    pub arrow_scale: f32,
+    pub max_speed: f32,
+    pub colour_map: ColourMap,
+    averages: Vec<Vec2>,
+}
+
+impl VelocityGrid {
+    fn cell_size(&self) -> f32 {
+        2.0 * BORDER_DISTANCE / self.resolution as f32
+    }
+
+    fn cell_center(&self, x: usize, y: usize) -> Vec2 {
+        (Vec2::new(x as f32, y as f32) + 0.5) * self.cell_size() - BORDER_DISTANCE
+    }
+
+    // Bilinear interpolation between cell centres.
+    fn sample(&self, position: Vec2) -> Vec2 {
+        let n = self.resolution as usize;
+        if self.averages.len() != n * n {
+            return Vec2::ZERO;
         }
+        let max = (n - 1) as f32;
+        let p = ((position + BORDER_DISTANCE) / self.cell_size() - 0.5).clamp(Vec2::ZERO, Vec2::splat(max));
+        let x0 = p.x.floor() as usize;
+        let y0 = p.y.floor() as usize;
+        let x1 = (x0 + 1).min(n - 1);
+        let y1 = (y0 + 1).min(n - 1);
+        let f = p - p.floor();
+        let at = |x: usize, y: usize| self.averages[y * n + x];
+        let bottom = at(x0, y0).lerp(at(x1, y0), f.x);
+        let top = at(x0, y1).lerp(at(x1, y1), f.x);
+        bottom.lerp(top, f.y)
     }
+
+    // Midpoint integration along the field, half a cell per step, until the
+    // flow stalls or leaves the arena.
+    fn streamline(&self, start: Vec2) -> Vec<Vec2> {
+        let step = self.cell_size() * 0.5;
+        let direction = |position: Vec2| self.sample(position).normalize_or_zero();
+        let mut position = start;
+        let mut points = Vec::with_capacity(STREAMLINE_STEPS);
+        for _i in 0..STREAMLINE_STEPS {
+            if self.sample(position).length() < MIN_STREAMLINE_SPEED || position.abs().max_element() > BORDER_DISTANCE {
+                break;
+            }
+            points.push(position);
+            let midpoint = position + direction(position) * step * 0.5;
+            position += direction(midpoint) * step;
+        }
+        points
+    }
+}
+
+fn spawn_density_overlay(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut image = Image::new_fill(
+        Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(2.0 * BORDER_DISTANCE)),
+                ..default()
+            },
+            texture: images.add(image),
+            transform: Transform::from_xyz(0.0, 0.0, -5.0),
+            ..default()
+        },
+        DensityOverlay
+    ));
 }
 
-fn update_density_probes_count(
-    mut q_probes: Query<(&mut DensityProbe, &Transform)>,
+fn update_density_grid(
+    mut grid: ResMut<DensityGrid>,
     q_particles: Query<&Transform, With<Particle>>,
 ) {
-    q_probes.par_iter_mut().for_each(|(mut probe, transform)| {
-        for particle in q_particles.iter() {
-            if (transform.translation - particle.translation).length() < probe.radius {
-                probe.count += 1;
-            }
+    let cells = (grid.resolution * grid.resolution) as usize;
+    grid.counts.clear();
+    grid.counts.resize(cells, 0);
+    for transform in q_particles.iter() {
+        if let Some(cell) = grid.cell(transform.translation.xy()) {
+            grid.counts[cell] += 1;
         }
-    });
+    }
 }
 
-fn update_velocity_probes_count(
-    mut q_probes: Query<(&mut VelocityProbe, &Transform)>,
+fn update_density_overlay(
+    grid: Res<DensityGrid>,
+    mut images: ResMut<Assets<Image>>,
+    mut q: Query<(&Handle<Image>, &mut Visibility), With<DensityOverlay>>,
+) {
+    let Ok((handle, mut visibility)) = q.get_single_mut() else {
+        return;
+    };
+    *visibility = if grid.visible { Visibility::Inherited } else { Visibility::Hidden };
+    if !grid.visible {
+        return;
+    }
+    let Some(image) = images.get_mut(handle) else {
+        return;
+    };
+
+    let resolution = grid.resolution;
+    if image.width() != resolution || image.height() != resolution {
+        image.resize(Extent3d { width: resolution, height: resolution, depth_or_array_layers: 1 });
+    }
+    for (cell, count) in grid.counts.iter().enumerate() {
+        let x = cell % resolution as usize;
+        let y = cell / resolution as usize;
+        // Image rows run top to bottom, the grid bottom to top.
+        let pixel = ((resolution as usize - 1 - y) * resolution as usize + x) * 4;
+        let rgba = if *count == 0 {
+            [0; 4]
+        } else {
+            let colour = grid.colour_map.sample(*count as f32 / grid.max_density);
+            colour.with_alpha(DENSITY_ALPHA).to_srgba().to_u8_array()
+        };
+        image.data[pixel..pixel + 4].copy_from_slice(&rgba);
+    }
+}
+
+fn update_velocity_grid(
+    mut grid: ResMut<VelocityGrid>,
     q_particles: Query<(&Transform, &Velocity), With<Particle>>,
 ) {
-    q_probes.par_iter_mut().for_each(|(mut probe, transform)| {
-        probe.velocity = Vec2::ZERO;
-        let mut count: u32 = 0;
-        for (particle, velocity) in q_particles.iter() {
-            if (transform.translation - particle.translation).length() < probe.radius {
-                probe.velocity += velocity.0;
-                count += 1;
+    if grid.display == VelocityDisplay::Off {
+        return;
+    }
+    let n = grid.resolution as usize;
+    let cell_size = grid.cell_size();
+    let mut sums = vec![Vec2::ZERO; n * n];
+    let mut counts = vec![0_u32; n * n];
+    for (transform, velocity) in q_particles.iter() {
+        let p = (transform.translation.xy() + BORDER_DISTANCE) / cell_size;
+        if p.cmplt(Vec2::ZERO).any() || p.cmpge(Vec2::splat(n as f32)).any() {
+            continue;
+        }
+        let cell = p.y as usize * n + p.x as usize;
+        sums[cell] += velocity.0;
+        counts[cell] += 1;
+    }
+    grid.averages = sums
+        .into_iter()
+        .zip(counts)
+        .map(|(sum, count)| if count > 0 { sum / count as f32 } else { Vec2::ZERO })
+        .collect();
+}
+
+fn draw_velocity_grid(
+    grid: Res<VelocityGrid>,
+    mut gizmos: Gizmos,
+) {
+    let n = grid.resolution as usize;
+    if grid.averages.len() != n * n {
+        return;
+    }
+    let cell_size = grid.cell_size();
+    let colour = |velocity: Vec2| grid.colour_map.sample(velocity.length() / grid.max_speed);
+
+    match grid.display {
+        VelocityDisplay::Off => {}
+        VelocityDisplay::Arrows => {
+            for y in 0..n {
+                for x in 0..n {
+                    let velocity = grid.averages[y * n + x];
+                    if velocity == Vec2::ZERO {
+                        continue;
+                    }
+                    let start = grid.cell_center(x, y);
+                    let length = (velocity.length() * grid.arrow_scale).min(cell_size);
+                    let end = start + velocity.normalize() * length;
+                    gizmos.arrow_2d(start, end, colour(velocity)).with_tip_length(length * 0.3);
+                }
             }
         }
-        probe.velocity /= count as f32;
-    });
+        VelocityDisplay::Streamlines => {
+            for y in (0..n).step_by(STREAMLINE_STRIDE) {
+                for x in (0..n).step_by(STREAMLINE_STRIDE) {
+                    let points = grid.streamline(grid.cell_center(x, y));
+                    if points.len() > 1 {
+                        gizmos.linestrip_gradient_2d(points.into_iter().map(|point| (point, colour(grid.sample(point)))));
+                    }
+                }
+            }
+        }
+    }
 }
 
-fn update_velocity_probes_visuals(
-    mut q: Query<(&VelocityProbe, &mut Transform)>,
+#[allow(clippy::type_complexity)]
+fn colour_particles(
+    assets: Res<ParticleAssets>,
+    density: Res<DensityGrid>,
+    mut mode: ResMut<ColourMode>,
+    mut q: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        &Charge,
+        &Species,
+        &Age,
+        &Acceleration,
+        Has<Lifetime>,
+        &mut ParticleColour,
+    ), With<Particle>>,
 ) {
-    for (probe, mut transform) in q.iter_mut() {
-        let angle = f32::atan(probe.velocity.y / probe.velocity.x);
-        if probe.velocity.x < 0.0 {
-            transform.rotation = Quat::from_rotation_z(angle + PI);
-        } else {
-            transform.rotation = Quat::from_rotation_z(angle);
+    if mode.quantity == ColourQuantity::Species {
+        // Particles with a lifetime are coloured as they age.
+        for (.., charge, species, _, _, has_lifetime, mut colour) in q.iter_mut() {
+            let palette = assets.material(species.0, charge.0);
+            if !has_lifetime && colour.0 != palette {
+                colour.0 = palette;
+            }
+        }
+        return;
+    }
+
+    let clusters = if mode.quantity == ColourQuantity::Cluster {
+        find_clusters(q.iter().map(|(entity, transform, ..)| (entity, transform.translation.xy())))
+    } else {
+        HashMap::default()
+    };
+
+    let values = q
+        .iter()
+        .map(|(entity, transform, velocity, _, _, age, acceleration, ..)| match mode.quantity {
+            ColourQuantity::Species => 0.0,
+            ColourQuantity::Speed => velocity.0.length(),
+            ColourQuantity::KineticEnergy => 0.5 * velocity.0.length_squared(),
+            ColourQuantity::Density => density.density_at(transform.translation.xy()) as f32,
+            ColourQuantity::Age => age.0,
+            ColourQuantity::Cluster => (clusters[&entity] as f32 * GOLDEN_RATIO).fract(),
+            ColourQuantity::Acceleration => acceleration.value.length(),
+        })
+        .collect::<Vec<_>>();
+
+    let (min, max) = if mode.quantity == ColourQuantity::Cluster {
+        (0.0, 1.0)
+    } else {
+        values.iter().fold((f32::MAX, f32::MIN), |(min, max), value| (min.min(*value), max.max(*value)))
+    };
+    if values.is_empty() {
+        return;
+    }
+    mode.min = min;
+    mode.max = max;
+
+    let range = (max - min).max(f32::EPSILON);
+    for ((.., mut colour), value) in q.iter_mut().zip(values) {
+        let gradient = assets.gradient(mode.map, (value - min) / range);
+        if colour.0 != gradient {
+            colour.0 = gradient;
+        }
+    }
+}
+
+// Single linkage clustering: particles closer than `CLUSTER_DISTANCE` share
+// a cluster. Returns a cluster id for every particle.
+fn find_clusters(particles: impl Iterator<Item = (Entity, Vec2)>) -> HashMap<Entity, usize> {
+    let particles = particles.collect::<Vec<_>>();
+    let mut parent = (0..particles.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let cell = |position: Vec2| (position / CLUSTER_DISTANCE).floor().as_ivec2();
+    let mut cells = HashMap::<IVec2, Vec<usize>>::default();
+    for (i, (_, position)) in particles.iter().enumerate() {
+        cells.entry(cell(*position)).or_default().push(i);
+    }
+
+    for (i, (_, position)) in particles.iter().enumerate() {
+        let center = cell(*position);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(neighbours) = cells.get(&(center + IVec2::new(dx, dy))) else {
+                    continue;
+                };
+                for &j in neighbours {
+                    if j > i && position.distance(particles[j].1) < CLUSTER_DISTANCE {
+                        let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                        parent[a] = b;
+                    }
+                }
+            }
         }
-        transform.scale.x = probe.velocity.length() / 1000.0;
-        transform.scale.y = probe.velocity.length() / 1000.0;
+    }
+
+    (0..particles.len())
+        .map(|i| (particles[i].0, root(&mut parent, i)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(positions: &[(Vec2, Vec2)]) -> World {
+        let mut world = World::new();
+        for (position, velocity) in positions {
+            world.spawn((Particle, Transform::from_translation(position.extend(0.0)), Velocity(*velocity)));
+        }
+        world
+    }
+
+    fn run<M>(world: &mut World, system: impl IntoSystemConfigs<M>) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(system);
+        schedule.run(world);
+    }
+
+    #[test]
+    fn density_is_binned_per_cell() {
+        let cell = 2.0 * BORDER_DISTANCE / 4.0;
+        let mut world = world(&[
+            (Vec2::splat(-BORDER_DISTANCE + 1.0), Vec2::ZERO),
+            (Vec2::splat(-BORDER_DISTANCE + cell - 1.0), Vec2::ZERO),
+            (Vec2::new(1.0, -1.0), Vec2::ZERO),
+            (Vec2::splat(BORDER_DISTANCE), Vec2::ZERO),
+            (Vec2::new(0.0, -2.0 * BORDER_DISTANCE), Vec2::ZERO),
+        ]);
+        world.insert_resource(DensityGrid {
+            resolution: 4,
+            max_density: 1.0,
+            colour_map: ColourMap::Magma,
+            visible: true,
+            counts: Vec::new(),
+        });
+        run(&mut world, update_density_grid);
+
+        let grid = world.resource::<DensityGrid>();
+        assert_eq!(grid.counts.len(), 16);
+        assert_eq!(grid.density_at(Vec2::splat(-BORDER_DISTANCE + cell / 2.0)), 2);
+        assert_eq!(grid.density_at(Vec2::new(cell / 2.0, -cell / 2.0)), 1);
+        assert_eq!(grid.density_at(Vec2::splat(cell / 2.0)), 0);
+        // Particles on or past the far edge fall outside the grid.
+        assert_eq!(grid.counts.iter().sum::<u32>(), 3);
+        assert_eq!(grid.density_at(Vec2::splat(BORDER_DISTANCE)), 0);
+    }
+
+    #[test]
+    fn density_is_rebinned_when_the_resolution_changes() {
+        let mut world = world(&[
+            (Vec2::new(-100.0, 100.0), Vec2::ZERO),
+            (Vec2::new(100.0, 100.0), Vec2::ZERO),
+        ]);
+        world.insert_resource(DensityGrid {
+            resolution: 2,
+            max_density: 1.0,
+            colour_map: ColourMap::Magma,
+            visible: true,
+            counts: Vec::new(),
+        });
+        run(&mut world, update_density_grid);
+        assert_eq!(world.resource::<DensityGrid>().counts, [0, 0, 1, 1]);
+
+        world.resource_mut::<DensityGrid>().resolution = 1;
+        run(&mut world, update_density_grid);
+        assert_eq!(world.resource::<DensityGrid>().counts, [2]);
+
+        world.resource_mut::<DensityGrid>().resolution = 8;
+        run(&mut world, update_density_grid);
+        let grid = world.resource::<DensityGrid>();
+        assert_eq!(grid.counts.len(), 64);
+        assert_eq!(grid.density_at(Vec2::new(-100.0, 100.0)), 1);
+        assert_eq!(grid.density_at(Vec2::new(-100.0, -100.0)), 0);
+    }
+
+    fn velocity_grid(resolution: u32) -> VelocityGrid {
+        VelocityGrid {
+            resolution,
+            display: VelocityDisplay::Arrows,
+            arrow_scale: 1.0,
+            max_speed: 1.0,
+            colour_map: ColourMap::Viridis,
+            averages: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn velocities_are_averaged_per_cell() {
+        let mut world = world(&[
+            (Vec2::new(-100.0, -100.0), Vec2::new(10.0, 0.0)),
+            (Vec2::new(-200.0, -300.0), Vec2::new(0.0, 20.0)),
+            (Vec2::new(100.0, 100.0), Vec2::new(-4.0, 0.0)),
+            (Vec2::splat(2.0 * BORDER_DISTANCE), Vec2::new(1000.0, 0.0)),
+        ]);
+        world.insert_resource(velocity_grid(2));
+        run(&mut world, update_velocity_grid);
+
+        // Empty cells are zero rather than NaN.
+        let grid = world.resource::<VelocityGrid>();
+        assert_eq!(grid.averages, [Vec2::new(5.0, 10.0), Vec2::ZERO, Vec2::ZERO, Vec2::new(-4.0, 0.0)]);
+
+        world.resource_mut::<VelocityGrid>().display = VelocityDisplay::Off;
+        world.spawn((Particle, Transform::default(), Velocity(Vec2::X)));
+        run(&mut world, update_velocity_grid);
+        assert_eq!(world.resource::<VelocityGrid>().averages[3], Vec2::new(-4.0, 0.0));
+    }
+
+    #[test]
+    fn streamlines_stop_where_the_flow_does() {
+        let mut grid = velocity_grid(4);
+        let cell = grid.cell_size();
+
+        // No flow, no line.
+        grid.averages = vec![Vec2::ZERO; 16];
+        assert!(grid.streamline(Vec2::ZERO).is_empty());
+
+        // A uniform flow to the right runs half a cell per step until it
+        // leaves the arena.
+        grid.averages = vec![Vec2::new(100.0, 0.0); 16];
+        let start = grid.cell_center(0, 1);
+        let points = grid.streamline(start);
+        assert!(points.len() > 1 && points.len() < STREAMLINE_STEPS);
+        assert!(points.windows(2).all(|pair| (pair[1] - pair[0]).distance(Vec2::new(cell * 0.5, 0.0)) < 1e-2));
+        assert!(points.iter().all(|point| point.x <= BORDER_DISTANCE && point.y == start.y));
+        assert!(points.last().unwrap().x + cell * 0.5 > BORDER_DISTANCE);
+
+        // A flow that dies off at the second column stops there.
+        grid.averages = (0..16).map(|i| if i % 4 < 2 { Vec2::new(100.0, 0.0) } else { Vec2::ZERO }).collect();
+        let points = grid.streamline(grid.cell_center(0, 1));
+        assert!(points.last().unwrap().x < grid.cell_center(2, 1).x);
     }
 }
@@ -1,103 +1,720 @@
-use bevy:: {
-    prelude::*,
-    sprite::MaterialMesh2dBundle,
-};
+use bevy::prelude::*;
 
 use std::{
-    time::Duration,
+    collections::HashMap,
     f32::consts::TAU,
+    fs,
+    time::Duration,
 };
 
-use rand::random;
+use rand::Rng;
 
 use crate::{
     particle::*,
     asset::ParticleAssets,
+    lifetime::{Lifetime, AgeFade},
+    ui::CursorPosition,
+    physics::{SimRng, SimulationConfig, gaussian},
+    forcefield::read_curve,
 };
 
 pub struct EmmiterPlugin;
 
 impl Plugin for EmmiterPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_emmiters);
-        app.add_systems(Update, emit_particles);
+        app.add_systems(Startup, (spawn_emmiters, load_emmiters));
+        app.add_systems(Update, (sync_emission_rate, move_emmiters, emit_particles).chain());
+    }
+}
+
+#[derive(Component)]
+pub struct Emmiter {
+    pub transform: Transform,
+    pub charge: ChargeMode,
+    pub timer: Timer,
+    pub burst: u32,
+    pub direction: f32,
+    pub spread: f32,
+    pub speed: Distribution,
+    pub shape: EmmiterShape,
+    pub max_particles: u32,
+    pub velocity: Vec2,
+    pub lifetime: Option<Lifetime>,
+}
+
+impl Emmiter {
+    pub fn new(position: Vec2) -> Emmiter {
+        Emmiter {
+            transform: Transform::from_translation(position.extend(0.0)),
+            charge: ChargeMode::Mix(vec![
+                SpawnKind { species: 0, charge: 1.0, weight: 1.0 },
+                SpawnKind { species: 0, charge: -1.0, weight: 1.0 },
+            ]),
+            timer: Timer::from_seconds(1.0 / SPAWN_RATE, TimerMode::Repeating),
+            burst: 1,
+            direction: 0.0,
+            spread: TAU,
+            speed: Distribution::Constant(SPAWN_VELOCITY),
+            shape: EmmiterShape::Point,
+            max_particles: MAX_PARTICLES_PER_EMMITER,
+            velocity: Vec2::ZERO,
+            lifetime: None,
+        }
     }
 }
 
 #[derive(Component)]
-struct Emmiter {
-    transform: Transform,
-    charge: Charge,
+pub struct EmmiterMotion {
+    pub path: MotionPath,
+    pub elapsed: f32,
+}
+
+// Positions are given as a function of the time since the emitter started
+// moving. Splines loop through their waypoints once every `duration`.
+pub enum MotionPath {
+    Linear { origin: Vec2, velocity: Vec2 },
+    Orbit { center: Vec2, radius: f32, angular_speed: f32, phase: f32 },
+    Lissajous { center: Vec2, amplitude: Vec2, frequency: Vec2, phase: f32 },
+    Spline { waypoints: Vec<Vec2>, duration: f32 },
+}
+
+#[derive(Component)]
+pub enum EmmiterAttachment {
+    Particle(Entity),
+    Cursor,
+}
+
+pub enum EmmiterShape {
+    Point,
+    Line { length: f32, angle: f32 },
+    Disc { radius: f32 },
+    Ring { radius: f32 },
+}
+
+pub enum Distribution {
+    Constant(f32),
+    Uniform { min: f32, max: f32 },
+    Normal { mean: f32, deviation: f32 },
+}
+
+// What each spawned particle is made of: a fixed kind, a weighted random
+// mix, a repeating sequence, or a single species with its charge drawn
+// from a distribution.
+pub enum ChargeMode {
+    Fixed(SpawnKind),
+    Mix(Vec<SpawnKind>),
+    Sequence { kinds: Vec<SpawnKind>, next: usize },
+    Distribution { species: usize, charge: Distribution },
+}
+
+pub struct SpawnKind {
+    pub species: usize,
+    pub charge: f32,
+    pub weight: f32,
 }
 
-#[derive(Resource)]
-struct EmmiterTimer {
-    timer: Timer
+#[derive(Component)]
+pub struct EmittedBy(pub Entity);
+
+impl EmmiterShape {
+    fn sample(&self, rng: &mut impl Rng) -> Vec2 {
+        match self {
+            EmmiterShape::Point => Vec2::ZERO,
+            EmmiterShape::Line { length, angle } => {
+                Vec2::from_angle(*angle) * (rng.gen::<f32>() - 0.5) * *length
+            }
+            EmmiterShape::Disc { radius } => {
+                Vec2::from_angle(rng.gen::<f32>() * TAU) * rng.gen::<f32>().sqrt() * *radius
+            }
+            EmmiterShape::Ring { radius } => {
+                Vec2::from_angle(rng.gen::<f32>() * TAU) * *radius
+            }
+        }
+    }
+}
+
+impl MotionPath {
+    fn position(&self, t: f32) -> Vec2 {
+        match self {
+            MotionPath::Linear { origin, velocity } => *origin + *velocity * t,
+            MotionPath::Orbit { center, radius, angular_speed, phase } => {
+                *center + Vec2::from_angle(angular_speed * t + phase) * *radius
+            }
+            MotionPath::Lissajous { center, amplitude, frequency, phase } => {
+                *center + *amplitude * Vec2::new(
+                    (frequency.x * t + phase).sin(),
+                    (frequency.y * t).sin(),
+                )
+            }
+            MotionPath::Spline { waypoints, duration } => {
+                let n = waypoints.len();
+                if n < 2 {
+                    return waypoints.first().copied().unwrap_or(Vec2::ZERO);
+                }
+                let s = if *duration > 0.0 { (t / duration).rem_euclid(1.0) * n as f32 } else { 0.0 };
+                let i = s.floor() as usize;
+                let point = |offset: usize| waypoints[(i + n + offset - 1) % n];
+                catmull_rom(point(0), point(1), point(2), point(3), s.fract())
+            }
+        }
+    }
+}
+
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+impl ChargeMode {
+    fn next(&mut self, rng: &mut impl Rng) -> (usize, f32) {
+        match self {
+            ChargeMode::Fixed(kind) => (kind.species, kind.charge),
+            ChargeMode::Mix(kinds) => {
+                let total: f32 = kinds.iter().map(|kind| kind.weight).sum();
+                let mut pick = rng.gen::<f32>() * total;
+                for kind in kinds.iter() {
+                    if pick < kind.weight {
+                        return (kind.species, kind.charge);
+                    }
+                    pick -= kind.weight;
+                }
+                kinds.last().map_or((0, 0.0), |kind| (kind.species, kind.charge))
+            }
+            ChargeMode::Sequence { kinds, next } => {
+                let Some(kind) = kinds.get(*next % kinds.len().max(1)) else {
+                    return (0, 0.0);
+                };
+                *next = (*next + 1) % kinds.len();
+                (kind.species, kind.charge)
+            }
+            ChargeMode::Distribution { species, charge } => (*species, charge.sample(rng)),
+        }
+    }
+}
+
+impl Distribution {
+    fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match self {
+            Distribution::Constant(speed) => *speed,
+            Distribution::Uniform { min, max } => min + rng.gen::<f32>() * (max - min),
+            Distribution::Normal { mean, deviation } => mean + deviation * gaussian(rng),
+        }
+    }
 }
 
 const EMMITER_COUNT: u16 = 10;
 const EMMITER_SPAWN_RANGE: f32 = 1000.0;
 const SPAWN_VELOCITY: f32 = 250.0;
-const SPAWN_TIME_MSEC: u64 = 100;
-const MAX_PARTICLE_COUNT: u16 = 2500;
+pub const SPAWN_RATE: f32 = 10.0;
+pub const MAX_PARTICLE_COUNT: usize = 2500;
+const MAX_PARTICLES_PER_EMMITER: u32 = 250;
+const ORBIT_RADIUS: f32 = 1500.0;
+const ORBIT_SPEED: f32 = 0.5;
+const EMMITERS_PATH: &str = "presets/emmiters.preset";
 
 fn spawn_emmiters(
     mut commands: Commands,
+    mut rng: ResMut<SimRng>,
 ) {
-    for _i in 0..EMMITER_COUNT {
-        let positive = true;//random::<bool>();
-        let x = random::<f32>() * EMMITER_SPAWN_RANGE - EMMITER_SPAWN_RANGE/2.0;
-        let y = random::<f32>() * EMMITER_SPAWN_RANGE - EMMITER_SPAWN_RANGE/2.0;
-
-        commands.spawn(
-            Emmiter {
-                transform: Transform::from_xyz(x, y, 0.0),
-                charge: if positive { Charge(1.0) } else { Charge(-1.0) },
+    for i in 0..EMMITER_COUNT {
+        let x = rng.0.gen::<f32>() * EMMITER_SPAWN_RANGE - EMMITER_SPAWN_RANGE/2.0;
+        let y = rng.0.gen::<f32>() * EMMITER_SPAWN_RANGE - EMMITER_SPAWN_RANGE/2.0;
+
+        let motion = (i == 0).then_some(EmmiterMotion {
+            path: MotionPath::Orbit {
+                center: Vec2::ZERO,
+                radius: ORBIT_RADIUS,
+                angular_speed: ORBIT_SPEED,
+                phase: 0.0,
+            },
+            elapsed: 0.0,
+        });
+        let position = motion
+            .as_ref()
+            .map_or(Vec2::new(x, y), |motion| motion.path.position(0.0));
+
+        let mut emmiter = commands.spawn(Emmiter::new(position));
+        if let Some(motion) = motion {
+            emmiter.insert(motion);
+        }
+    }
+}
+
+// Adds the emitters listed in `EMMITERS_PATH`, if there are any.
+fn load_emmiters(
+    mut commands: Commands,
+) {
+    let emmiters = match fs::read_to_string(EMMITERS_PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|emmiters| read_emmiters(&emmiters))
+    {
+        Ok(emmiters) => emmiters,
+        Err(error) => {
+            warn!("Could not load {EMMITERS_PATH}: {error}");
+            return;
+        }
+    };
+    for (emmiter, motion) in emmiters {
+        let mut emmiter = commands.spawn(emmiter);
+        if let Some(motion) = motion {
+            emmiter.insert(motion);
+        }
+    }
+}
+
+// One emitter per line, `emmiter <x> <y>` followed by any of
+//   burst <count>, cap <count>        particles per tick and alive at once
+//   cone <direction> <spread>         emission angles in radians
+//   speed <distribution>              constant <value>, uniform <min> <max>
+//                                     or normal <mean> <deviation>
+//   point, line <length> <angle>,     where particles appear around the
+//   disc <radius>, ring <radius>      emitter
+//   fixed <species> <charge>,         what the particles are made of, with
+//   mix <kinds>, sequence <kinds>,    kinds written `species:charge:weight,...`
+//   charges <species> <distribution>  and the weights left out for sequences
+//   linear <velocity x> <velocity y>  motion starting from the emitter's
+//   orbit <radius> <speed> <phase>    position, with spline waypoints
+//   lissajous <amplitude x> <y>       written `x:y,x:y,...` relative to it
+//     <frequency x> <y> <phase>
+//   spline <duration> <waypoints>
+//   lifetime <seconds> <fade>         how long particles live and whether
+//                                     they fade, shrink or stay as they are
+//   age-charge <curve>,               charge and palette species over the
+//   age-colour <curve>                lifetime, `t:value,...` with t in 0-1
+fn read_emmiters(emmiters: &str) -> Result<Vec<(Emmiter, Option<EmmiterMotion>)>, String> {
+    emmiters
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| read_emmiter(line).map_err(|error| format!("{error} in `{line}`")))
+        .collect()
+}
+
+fn read_emmiter(line: &str) -> Result<(Emmiter, Option<EmmiterMotion>), String> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("emmiter") {
+        return Err("expected an emmiter".to_string());
+    }
+    let mut number = || read_number(words.next());
+    let position = Vec2::new(number()?, number()?);
+    let mut emmiter = Emmiter::new(position);
+    let mut path = None;
+
+    while let Some(option) = words.next() {
+        let mut number = || read_number(words.next());
+        match option {
+            "burst" => emmiter.burst = read_count(words.next())?,
+            "cap" => emmiter.max_particles = read_count(words.next())?,
+            "cone" => (emmiter.direction, emmiter.spread) = (number()?, number()?),
+            "speed" => emmiter.speed = read_distribution(&mut words)?,
+            "point" => emmiter.shape = EmmiterShape::Point,
+            "line" => emmiter.shape = EmmiterShape::Line { length: number()?, angle: number()? },
+            "disc" => emmiter.shape = EmmiterShape::Disc { radius: number()? },
+            "ring" => emmiter.shape = EmmiterShape::Ring { radius: number()? },
+            "fixed" => emmiter.charge = ChargeMode::Fixed(SpawnKind {
+                species: read_count(words.next())? as usize,
+                charge: read_number(words.next())?,
+                weight: 1.0,
+            }),
+            "mix" => emmiter.charge = ChargeMode::Mix(read_kinds(words.next(), true)?),
+            "sequence" => emmiter.charge = ChargeMode::Sequence { kinds: read_kinds(words.next(), false)?, next: 0 },
+            "charges" => emmiter.charge = ChargeMode::Distribution {
+                species: read_count(words.next())? as usize,
+                charge: read_distribution(&mut words)?,
+            },
+            "linear" => path = Some(MotionPath::Linear {
+                origin: position,
+                velocity: Vec2::new(number()?, number()?),
+            }),
+            "orbit" => path = Some(MotionPath::Orbit {
+                center: position,
+                radius: number()?,
+                angular_speed: number()?,
+                phase: number()?,
+            }),
+            "lissajous" => path = Some(MotionPath::Lissajous {
+                center: position,
+                amplitude: Vec2::new(number()?, number()?),
+                frequency: Vec2::new(number()?, number()?),
+                phase: number()?,
+            }),
+            "spline" => path = Some(MotionPath::Spline {
+                duration: number()?,
+                waypoints: read_waypoints(words.next())?
+                    .into_iter()
+                    .map(|waypoint| position + waypoint)
+                    .collect(),
+            }),
+            "lifetime" => emmiter.lifetime = Some(Lifetime {
+                duration: number()?,
+                fade: match words.next() {
+                    Some("none") => AgeFade::None,
+                    Some("fade") => AgeFade::Fade,
+                    Some("shrink") => AgeFade::Shrink,
+                    Some(word) => return Err(format!("unknown fade `{word}`")),
+                    None => return Err("missing fade".to_string()),
+                },
+                charge: None,
+                colour: None,
+            }),
+            "age-charge" | "age-colour" => {
+                let lifetime = emmiter.lifetime.as_mut().ok_or("curve without a lifetime")?;
+                let curve = Some(read_curve(words.next().ok_or("missing curve")?)?);
+                match option {
+                    "age-charge" => lifetime.charge = curve,
+                    _ => lifetime.colour = curve,
+                }
             }
-        );
+            _ => return Err(format!("unknown option `{option}`")),
+        }
     }
+    let motion = path.map(|path| {
+        emmiter.transform.translation = path.position(0.0).extend(0.0);
+        EmmiterMotion { path, elapsed: 0.0 }
+    });
+    Ok((emmiter, motion))
+}
+
+fn read_waypoints(word: Option<&str>) -> Result<Vec<Vec2>, String> {
+    word.ok_or("missing waypoints")?
+        .split(',')
+        .map(|waypoint| {
+            let (x, y) = waypoint.split_once(':').ok_or_else(|| format!("invalid waypoint `{waypoint}`"))?;
+            Ok(Vec2::new(read_number(Some(x))?, read_number(Some(y))?))
+        })
+        .collect()
+}
+
+fn read_distribution<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<Distribution, String> {
+    match words.next() {
+        Some("constant") => Ok(Distribution::Constant(read_number(words.next())?)),
+        Some("uniform") => Ok(Distribution::Uniform {
+            min: read_number(words.next())?,
+            max: read_number(words.next())?,
+        }),
+        Some("normal") => Ok(Distribution::Normal {
+            mean: read_number(words.next())?,
+            deviation: read_number(words.next())?,
+        }),
+        Some(word) => Err(format!("unknown distribution `{word}`")),
+        None => Err("missing distribution".to_string()),
+    }
+}
+
+fn read_kinds(word: Option<&str>, weighted: bool) -> Result<Vec<SpawnKind>, String> {
+    word.ok_or("missing kinds")?
+        .split(',')
+        .map(|kind| {
+            let values = kind.split(':').collect::<Vec<_>>();
+            let kind = match (values.as_slice(), weighted) {
+                ([species, charge, weight], true) => (*species, *charge, Some(*weight)),
+                ([species, charge], false) => (*species, *charge, None),
+                _ => return Err(format!("invalid kind `{kind}`")),
+            };
+            Ok(SpawnKind {
+                species: read_count(Some(kind.0))? as usize,
+                charge: read_number(Some(kind.1))?,
+                weight: kind.2.map_or(Ok(1.0), |weight| read_number(Some(weight)))?.max(0.0),
+            })
+        })
+        .collect()
+}
 
-    commands.insert_resource(
-        EmmiterTimer {
-            timer: Timer::new(
-                       Duration::from_millis(SPAWN_TIME_MSEC),
-                       TimerMode::Repeating),
+fn read_number(word: Option<&str>) -> Result<f32, String> {
+    let word = word.ok_or("missing number")?;
+    word.parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("invalid number `{word}`"))
+}
+
+fn read_count(word: Option<&str>) -> Result<u32, String> {
+    let word = word.ok_or("missing count")?;
+    word.parse::<u32>().map_err(|_| format!("invalid count `{word}`"))
+}
+
+fn sync_emission_rate(
+    config: Res<SimulationConfig>,
+    mut q: Query<&mut Emmiter>,
+) {
+    if !(config.emission_rate.is_finite() && config.emission_rate > 0.0) {
+        return;
+    }
+    let period = Duration::from_secs_f32(1.0 / config.emission_rate);
+    for mut emmiter in q.iter_mut() {
+        if (config.is_changed() || emmiter.is_added()) && emmiter.timer.duration() != period {
+            emmiter.timer.set_duration(period);
         }
-    );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn move_emmiters(
+    mut commands: Commands,
+    time: Res<Time>,
+    cursor: Res<CursorPosition>,
+    mut q: Query<(Entity, &mut Emmiter, Option<&mut EmmiterMotion>, Option<Ref<EmmiterAttachment>>)>,
+    q_particles: Query<&Transform, With<Particle>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut emmiter, motion, attachment) in q.iter_mut() {
+        let previous = emmiter.transform.translation.xy();
+        let target = match (attachment.as_deref(), motion) {
+            (Some(EmmiterAttachment::Particle(particle)), _) => {
+                match q_particles.get(*particle) {
+                    Ok(transform) => transform.translation.xy(),
+                    Err(_) => {
+                        commands.entity(entity).remove::<EmmiterAttachment>();
+                        previous
+                    }
+                }
+            }
+            (Some(EmmiterAttachment::Cursor), _) => cursor.0.unwrap_or(previous),
+            (None, Some(mut motion)) => {
+                motion.elapsed += dt;
+                motion.path.position(motion.elapsed)
+            }
+            (None, None) => previous,
+        };
+        emmiter.transform.translation = target.extend(emmiter.transform.translation.z);
+        // Attaching jumps the emitter over, which shouldn't fling particles.
+        let attached = attachment.is_some_and(|attachment| attachment.is_changed());
+        emmiter.velocity = if dt > 0.0 && !attached { (target - previous) / dt } else { Vec2::ZERO };
+    }
 }
 
 fn emit_particles(
     mut commands: Commands,
     assets: Res<ParticleAssets>,
     time: Res<Time>,
-    mut counter: ResMut<ParticleCounter>,
-    mut emitter_timer: ResMut<EmmiterTimer>,
-    q: Query<&Emmiter>,
+    counts: Res<ParticleCounts>,
+    mut rng: ResMut<SimRng>,
+    mut q: Query<(Entity, &mut Emmiter)>,
+    q_emitted: Query<&EmittedBy>,
 ) {
-    emitter_timer.timer.tick(time.delta());
-    if emitter_timer.timer.finished() && counter.0 < MAX_PARTICLE_COUNT {
-        for emmiter in q.iter() {
-            let dir = random::<f32>() * TAU;
-            let vel = Vec2 {
-                x: dir.sin() * SPAWN_VELOCITY,
-                y: dir.cos() * SPAWN_VELOCITY,
-            };
-            commands.spawn((
-                ParticleBundle {
-                    velocity: Velocity(vel),
-                    charge: Charge(emmiter.charge.0),
-                    cancelled: Cancelled(false),
-                    particle: Particle
-                },
-                MaterialMesh2dBundle {
-                    mesh: assets.circle.clone(),
-                    material: if emmiter.charge.0 > 0.0 { assets.red.clone() } else { assets.blue.clone() },
-                    transform: emmiter.transform, 
-                    ..default()
-                }
-            ));
+    // Spawns only show up in `counts` once the commands are applied.
+    let mut total = counts.total;
+    let mut emitted = HashMap::<Entity, u32>::new();
+    for emitted_by in q_emitted.iter() {
+        *emitted.entry(emitted_by.0).or_default() += 1;
+    }
+
+    for (entity, mut emmiter) in q.iter_mut() {
+        emmiter.timer.tick(time.delta());
+        let alive = emitted.get(&entity).copied().unwrap_or(0);
+        let pending = emmiter.timer.times_finished_this_tick() * emmiter.burst;
+        let allowed = emmiter.max_particles.saturating_sub(alive);
+        for _i in 0..pending.min(allowed) {
+            if total >= MAX_PARTICLE_COUNT {
+                break;
+            }
+            total += 1;
+            let dir = emmiter.direction + (rng.0.gen::<f32>() - 0.5) * emmiter.spread;
+            let vel = Vec2::from_angle(dir) * emmiter.speed.sample(&mut rng.0) + emmiter.velocity;
+            let position = emmiter.transform.translation.xy() + emmiter.shape.sample(&mut rng.0);
+            let (species, charge) = emmiter.charge.next(&mut rng.0);
+            let mut particle = spawn_particle(&mut commands, &assets, position, vel, species, charge);
+            particle.insert(EmittedBy(entity));
+            if let Some(lifetime) = &emmiter.lifetime {
+                particle.insert(lifetime.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn emmiter_presets_are_read() {
+        let emmiters = read_emmiters(include_str!("../presets/emmiters.preset"))
+            .unwrap()
+            .into_iter()
+            .map(|(emmiter, _)| emmiter)
+            .collect::<Vec<_>>();
+        assert_eq!(emmiters.len(), 2);
+        assert!(matches!(emmiters[0].shape, EmmiterShape::Ring { radius } if radius == 150.0));
+        assert!(matches!(emmiters[0].speed, Distribution::Normal { mean, deviation } if mean == 250.0 && deviation == 50.0));
+        assert!(matches!(&emmiters[0].charge, ChargeMode::Mix(kinds) if kinds.len() == 2 && kinds[0].weight == 2.0));
+        assert!(matches!(&emmiters[1].charge, ChargeMode::Sequence { kinds, next: 0 } if kinds[1].charge == -1.0));
+
+        let (emmiter, motion) = read_emmiter("emmiter 100 0 spline 4 0:0,10:0,10:10").unwrap();
+        let Some(EmmiterMotion { path: MotionPath::Spline { waypoints, duration }, .. }) = motion else {
+            panic!("expected a spline");
+        };
+        assert_eq!(duration, 4.0);
+        assert_eq!(waypoints, [Vec2::new(100.0, 0.0), Vec2::new(110.0, 0.0), Vec2::new(110.0, 10.0)]);
+        assert_eq!(emmiter.transform.translation, Vec3::new(100.0, 0.0, 0.0));
+
+        let (emmiter, _) = read_emmiter("emmiter 0 0 lifetime 5 shrink age-charge 0:1,1:-1 age-colour 0:0,1:3").unwrap();
+        let lifetime = emmiter.lifetime.unwrap();
+        assert_eq!(lifetime.duration, 5.0);
+        assert!(matches!(lifetime.fade, AgeFade::Shrink));
+        assert_eq!(lifetime.charge.map(|curve| curve.sample(0.5)), Some(0.0));
+        assert_eq!(lifetime.colour.map(|curve| curve.sample(1.0)), Some(3.0));
+
+        let (emmiter, motion) = read_emmiter("emmiter 10 -20 burst 3 cap 7 cone 1 0.5 disc 40 speed uniform 1 2").unwrap();
+        assert!(motion.is_none());
+        assert!(emmiter.lifetime.is_none());
+        assert_eq!(emmiter.transform.translation, Vec3::new(10.0, -20.0, 0.0));
+        assert_eq!((emmiter.burst, emmiter.max_particles), (3, 7));
+        assert_eq!((emmiter.direction, emmiter.spread), (1.0, 0.5));
+        assert!(matches!(emmiter.shape, EmmiterShape::Disc { radius } if radius == 40.0));
+        assert!(matches!(emmiter.speed, Distribution::Uniform { min, max } if min == 1.0 && max == 2.0));
+    }
+
+    #[test]
+    fn bad_emmiter_presets_are_rejected() {
+        for line in [
+            "field 0 0",
+            "emmiter 0",
+            "emmiter 0 NaN",
+            "emmiter 0 0 burst -1",
+            "emmiter 0 0 speed sometimes 3",
+            "emmiter 0 0 ring",
+            "emmiter 0 0 sparkle",
+            "emmiter 0 0 fixed -1 1",
+            "emmiter 0 0 mix 0:1",
+            "emmiter 0 0 sequence 0:1:1",
+            "emmiter 0 0 charges 0",
+            "emmiter 0 0 orbit 10 1",
+            "emmiter 0 0 spline 5 1:2,3",
+            "emmiter 0 0 lifetime 5",
+            "emmiter 0 0 lifetime 5 melt",
+            "emmiter 0 0 age-charge 0:1,1:0",
+        ] {
+            assert!(read_emmiters(line).is_err(), "accepted `{line}`");
+        }
+    }
+
+    fn kind(species: usize, charge: f32, weight: f32) -> SpawnKind {
+        SpawnKind { species, charge, weight }
+    }
+
+    #[test]
+    fn charge_mixes_follow_their_weights() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut mix = ChargeMode::Mix(vec![kind(0, 1.0, 3.0), kind(1, -1.0, 1.0), kind(2, 0.0, 0.0)]);
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[mix.next(&mut rng).0] += 1;
+        }
+        assert!((2800..3200).contains(&counts[0]), "{counts:?}");
+        assert_eq!(counts[2], 0);
+
+        // Without any weight the last kind is used rather than nothing.
+        let mut unweighted = ChargeMode::Mix(vec![kind(0, 1.0, 0.0), kind(1, -1.0, 0.0)]);
+        assert_eq!(unweighted.next(&mut rng), (1, -1.0));
+        assert_eq!(ChargeMode::Mix(Vec::new()).next(&mut rng), (0, 0.0));
+    }
+
+    #[test]
+    fn charge_sequences_wrap_around() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sequence = ChargeMode::Sequence { kinds: vec![kind(0, 1.0, 1.0), kind(1, -1.0, 1.0), kind(2, 0.5, 1.0)], next: 0 };
+        let spawned = (0..7).map(|_| sequence.next(&mut rng).0).collect::<Vec<_>>();
+        assert_eq!(spawned, [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(ChargeMode::Sequence { kinds: Vec::new(), next: 3 }.next(&mut rng), (0, 0.0));
+
+        assert_eq!(ChargeMode::Fixed(kind(4, -2.0, 1.0)).next(&mut rng), (4, -2.0));
+        let mut charges = ChargeMode::Distribution { species: 5, charge: Distribution::Uniform { min: -1.0, max: 1.0 } };
+        for _ in 0..100 {
+            let (species, charge) = charges.next(&mut rng);
+            assert!(species == 5 && (-1.0..=1.0).contains(&charge));
+        }
+    }
+
+    #[test]
+    fn motion_paths_follow_their_shape() {
+        let close = |a: Vec2, b: Vec2| a.distance(b) < 1e-3;
+
+        let orbit = MotionPath::Orbit { center: Vec2::new(5.0, 0.0), radius: 10.0, angular_speed: 2.0, phase: 0.0 };
+        assert!(close(orbit.position(0.0), Vec2::new(15.0, 0.0)));
+        assert!(close(orbit.position(TAU / 8.0), Vec2::new(5.0, 10.0)));
+        assert!(close(orbit.position(TAU / 2.0), orbit.position(0.0)));
+
+        let linear = MotionPath::Linear { origin: Vec2::ONE, velocity: Vec2::new(2.0, -1.0) };
+        assert!(close(linear.position(3.0), Vec2::new(7.0, -2.0)));
+
+        let lissajous = MotionPath::Lissajous { center: Vec2::ZERO, amplitude: Vec2::new(4.0, 2.0), frequency: Vec2::new(1.0, 2.0), phase: 0.0 };
+        assert!(close(lissajous.position(TAU / 4.0), Vec2::new(4.0, 0.0)));
+        assert!(close(lissajous.position(TAU), Vec2::ZERO));
+    }
+
+    #[test]
+    fn splines_loop_through_their_waypoints() {
+        let waypoints = vec![Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)];
+        let spline = MotionPath::Spline { waypoints: waypoints.clone(), duration: 8.0 };
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            assert!(spline.position(i as f32 * 2.0).distance(*waypoint) < 1e-3);
+        }
+        assert!(spline.position(8.0).distance(waypoints[0]) < 1e-3);
+        assert!(spline.position(-2.0).distance(waypoints[3]) < 1e-3);
+
+        // Degenerate splines stay put instead of producing NaN.
+        for duration in [0.0, -1.0] {
+            let spline = MotionPath::Spline { waypoints: waypoints.clone(), duration };
+            assert_eq!(spline.position(3.0), waypoints[0]);
+        }
+        assert_eq!(MotionPath::Spline { waypoints: vec![Vec2::ONE], duration: 1.0 }.position(0.5), Vec2::ONE);
+        assert_eq!(MotionPath::Spline { waypoints: Vec::new(), duration: 1.0 }.position(0.5), Vec2::ZERO);
+    }
+
+    #[test]
+    fn attaching_resets_the_emmiter_velocity() {
+        const DT: f32 = 0.1;
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(DT));
+        world.insert_resource(time);
+        world.insert_resource(CursorPosition(None));
+        let particle = world.spawn((Particle, Transform::from_xyz(100.0, 0.0, 0.0))).id();
+        let emmiter = world.spawn(Emmiter::new(Vec2::ZERO)).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(move_emmiters);
+        schedule.run(&mut world);
+
+        // The jump onto the particle doesn't count as movement.
+        world.entity_mut(emmiter).insert(EmmiterAttachment::Particle(particle));
+        schedule.run(&mut world);
+        let state = world.get::<Emmiter>(emmiter).unwrap();
+        assert_eq!(state.transform.translation.xy(), Vec2::new(100.0, 0.0));
+        assert_eq!(state.velocity, Vec2::ZERO);
+
+        // Following it afterwards does.
+        world.get_mut::<Transform>(particle).unwrap().translation.x = 110.0;
+        schedule.run(&mut world);
+        let velocity = world.get::<Emmiter>(emmiter).unwrap().velocity;
+        assert!(velocity.distance(Vec2::new(10.0 / DT, 0.0)) < 1e-2, "velocity {velocity}");
+    }
+
+    #[test]
+    fn emmiters_keep_ticking_past_the_particle_cap() {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(1.5 / SPAWN_RATE));
+        world.insert_resource(time);
+        world.insert_resource(ParticleAssets::placeholder());
+        let mut counts = ParticleCounts::default();
+        counts.total = MAX_PARTICLE_COUNT;
+        world.insert_resource(counts);
+        world.insert_resource(SimRng(StdRng::seed_from_u64(0)));
+        let emmiters = [world.spawn(Emmiter::new(Vec2::ZERO)).id(), world.spawn(Emmiter::new(Vec2::X)).id()];
+        let mut schedule = Schedule::default();
+        schedule.add_systems(emit_particles);
+        schedule.run(&mut world);
 
-            counter.0 += 1;
+        for emmiter in emmiters {
+            let timer = &world.get::<Emmiter>(emmiter).unwrap().timer;
+            assert_eq!(timer.times_finished_this_tick(), 1);
         }
+        assert_eq!(world.query::<&EmittedBy>().iter(&world).count(), 0);
     }
 }
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use crate::{
+    particle::*,
+    physics::SimulationConfig,
+};
+
+pub struct ExternalFieldPlugin;
+
+impl Plugin for ExternalFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_external_fields);
+    }
+}
+
+// Gravity, a uniform electric field and a magnetic field pointing out of the
+// screen, all set through the `SimulationConfig`.
+fn apply_external_fields(
+    time: Res<Time>,
+    config: Res<SimulationConfig>,
+    mut q: Query<(&mut Velocity, &Charge)>,
+) {
+    let dt = time.delta_seconds();
+    let SimulationConfig { gravity, electric_field, magnetic_field, .. } = *config;
+    q.par_iter_mut().for_each(|(mut velocity, charge)| {
+        velocity.0 += (gravity + charge.0 * electric_field) * dt;
+        // q v × B only turns the velocity, so rotate it by the exact
+        // cyclotron angle instead of integrating the force. This keeps the
+        // speed constant and the orbits closed.
+        velocity.0 = Vec2::from_angle(-charge.0 * magnetic_field * dt).rotate(velocity.0);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::TAU, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn charges_orbit_in_a_uniform_magnetic_field() {
+        const DT: f32 = 0.001;
+        const SPEED: f32 = 100.0;
+        const FIELD: f32 = 2.0;
+
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(DT));
+        world.insert_resource(time);
+        world.insert_resource(SimulationConfig {
+            magnetic_field: FIELD,
+            ..default()
+        });
+        let particle = world.spawn((Velocity(Vec2::new(SPEED, 0.0)), Charge(1.0))).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_external_fields);
+
+        // A positive charge turns clockwise around a centre one cyclotron
+        // radius below its start and comes back after one period.
+        let radius = SPEED / FIELD;
+        let centre = Vec2::new(0.0, -radius);
+        let steps = (TAU / FIELD / DT).round() as usize;
+        let mut position = Vec2::ZERO;
+        for _ in 0..steps {
+            schedule.run(&mut world);
+            position += world.get::<Velocity>(particle).unwrap().0 * DT;
+            assert!((position.distance(centre) - radius).abs() < 0.01 * radius);
+        }
+        assert!(position.length() < 0.01 * radius, "ended at {position}");
+        assert!((world.get::<Velocity>(particle).unwrap().0.length() - SPEED).abs() < 1e-2);
+    }
+}
@@ -3,9 +3,21 @@ use bevy::{
     sprite::MaterialMesh2dBundle,
 };
 
+use rand::Rng;
+
 use crate:: {
     particle::*,
     asset::ForcefieldAssets,
+    collider::BORDER_DISTANCE,
+    curve::Curve,
+    noise,
+    physics::SimRng,
+    vectorfield::{VectorField, VectorFieldPlugin},
+};
+
+use std::{
+    f32::consts::TAU,
+    fs,
 };
 
 pub struct ForcefieldPlugin {
@@ -14,24 +26,173 @@ pub struct ForcefieldPlugin {
 
 impl Plugin for ForcefieldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_forcefield);
+        app.add_plugins(VectorFieldPlugin);
+        app.insert_resource(ArenaNoise(None));
+        app.add_systems(Startup, (spawn_forcefield, load_forcefields));
+        app.add_systems(Update, sync_arena_noise);
         if self.parallel {
-            app.add_systems(Update, apply_forcefields_single_thread);
-        } else {
             app.add_systems(Update, apply_forcefields_parallel);
+        } else {
+            app.add_systems(Update, apply_forcefields_single_thread);
         }
     }
 }
 
 #[derive(Component)]
 pub struct Forcefield {
-    rect: Rect,
-    force: Vec2,
+    pub rect: Rect,
+    pub kind: ForcefieldKind,
+    pub modulation: Modulation,
+}
+
+pub enum ForcefieldKind {
+    Uniform(Vec2),
+    Noise {
+        noise: NoiseType,
+        seed: u32,
+        scale: f32,
+        strength: f32,
+        evolution: f32,
+    },
+    Sampled {
+        field: Handle<VectorField>,
+        strength: f32,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NoiseType {
+    Curl,
+    Perlin,
+    Simplex,
+}
+
+// Noise over the whole arena, off unless picked. Each time it is turned on
+// the field gets a new seed from `SimRng`.
+#[derive(Resource)]
+pub struct ArenaNoise(pub Option<NoiseType>);
+
+impl ArenaNoise {
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            None => "off",
+            Some(NoiseType::Curl) => "curl",
+            Some(NoiseType::Perlin) => "Perlin",
+            Some(NoiseType::Simplex) => "simplex",
+        }
+    }
+
+    pub fn next(&self) -> ArenaNoise {
+        ArenaNoise(match self.0 {
+            None => Some(NoiseType::Curl),
+            Some(NoiseType::Curl) => Some(NoiseType::Perlin),
+            Some(NoiseType::Perlin) => Some(NoiseType::Simplex),
+            Some(NoiseType::Simplex) => None,
+        })
+    }
+}
+
+#[derive(Component)]
+struct ArenaNoiseField;
+
+// Scales and rotates the force of a field over time. `windows` lists the
+// (start, end) intervals in seconds where the field is active, an empty list
+// keeps it on forever.
+#[derive(Default)]
+pub struct Modulation {
+    pub strength: Waveform,
+    pub angle: Option<Curve>,
+    pub windows: Vec<(f32, f32)>,
+}
+
+#[derive(Default)]
+pub enum Waveform {
+    #[default]
+    Constant,
+    Sine { frequency: f32, phase: f32 },
+    Square { frequency: f32, duty: f32 },
+    Ramp { duration: f32 },
+    Keyframes { curve: Curve, looping: bool },
+}
+
+impl Waveform {
+    fn sample(&self, t: f32) -> f32 {
+        match self {
+            Waveform::Constant => 1.0,
+            Waveform::Sine { frequency, phase } => (t * frequency * TAU + phase).sin(),
+            Waveform::Square { frequency, duty } => {
+                if (t * frequency).fract() < *duty { 1.0 } else { -1.0 }
+            }
+            Waveform::Ramp { duration } => {
+                if *duration > 0.0 { (t / duration).clamp(0.0, 1.0) } else { 1.0 }
+            }
+            Waveform::Keyframes { curve, looping } => {
+                if *looping { curve.sample_looped(t) } else { curve.sample(t) }
+            }
+        }
+    }
+}
+
+impl Modulation {
+    fn is_active(&self, t: f32) -> bool {
+        self.windows.is_empty()
+            || self.windows.iter().any(|(start, end)| t >= *start && t < *end)
+    }
+
+    fn apply(&self, force: Vec2, t: f32) -> Vec2 {
+        let strength = self.strength.sample(t);
+        match &self.angle {
+            Some(angle) => Vec2::from_angle(angle.sample(t)).rotate(force) * strength,
+            None => force * strength,
+        }
+    }
+}
+
+impl Forcefield {
+    fn force_at(&self, position: Vec2, time: f32, fields: &Assets<VectorField>) -> Vec2 {
+        if !self.rect.contains(position) || !self.modulation.is_active(time) {
+            return Vec2::ZERO;
+        }
+        let force = match &self.kind {
+            ForcefieldKind::Uniform(force) => *force,
+            ForcefieldKind::Noise { noise, seed, scale, strength, evolution } => {
+                let p = position / *scale;
+                let t = time * evolution;
+                let value = match noise {
+                    NoiseType::Curl => noise::curl(*seed, p, t),
+                    NoiseType::Perlin => Vec2::new(
+                        noise::perlin(*seed, p.extend(t)),
+                        noise::perlin(seed.wrapping_add(1), p.extend(t)),
+                    ),
+                    NoiseType::Simplex => Vec2::new(
+                        noise::simplex(*seed, p.extend(t)),
+                        noise::simplex(seed.wrapping_add(1), p.extend(t)),
+                    ),
+                };
+                value * *strength
+            }
+            ForcefieldKind::Sampled { field, strength } => {
+                let Some(field) = fields.get(field) else {
+                    return Vec2::ZERO;
+                };
+                let uv = (position - self.rect.min) / self.rect.size();
+                field.sample(uv) * *strength
+            }
+        };
+        self.modulation.apply(force, time)
+    }
 }
 
+const NOISE_SCALE: f32 = 1500.0;
+const NOISE_STRENGTH: f32 = 150.0;
+const NOISE_EVOLUTION: f32 = 0.05;
+const SAMPLED_STRENGTH: f32 = 400.0;
+const FORCEFIELDS_PATH: &str = "presets/forcefields.preset";
+
 fn spawn_forcefield(
     mut commands: Commands,
     assets: Res<ForcefieldAssets>,
+    asset_server: Res<AssetServer>,
 ) {
     commands.spawn((
         Forcefield {
@@ -39,7 +200,8 @@ fn spawn_forcefield(
                 min: Vec2 { x: -500.0, y: -500.0 },
                 max: Vec2 { x: 500.0, y: 500.0 }
             },
-            force: Vec2 { x: (500.0), y: (0.0) }
+            kind: ForcefieldKind::Uniform(Vec2 { x: (500.0), y: (0.0) }),
+            modulation: Modulation::default()
         },
         MaterialMesh2dBundle {
             mesh: assets.triangle.clone(),
@@ -48,32 +210,267 @@ fn spawn_forcefield(
             ..default()
         }
     ));
+
+    commands.spawn(
+        Forcefield {
+            rect: Rect {
+                min: Vec2 { x: -3000.0, y: -3000.0 },
+                max: Vec2 { x: -1000.0, y: -1000.0 }
+            },
+            kind: ForcefieldKind::Sampled {
+                field: asset_server.load("fields/vortex.field.csv"),
+                strength: SAMPLED_STRENGTH,
+            },
+            modulation: Modulation::default()
+        }
+    );
+}
+
+// Adds the uniform fields listed in `FORCEFIELDS_PATH`, if there are any.
+fn load_forcefields(
+    mut commands: Commands,
+    assets: Res<ForcefieldAssets>,
+) {
+    let forcefields = match fs::read_to_string(FORCEFIELDS_PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|forcefields| read_forcefields(&forcefields))
+    {
+        Ok(forcefields) => forcefields,
+        Err(error) => {
+            warn!("Could not load {FORCEFIELDS_PATH}: {error}");
+            return;
+        }
+    };
+    for forcefield in forcefields {
+        let transform = Transform::from_translation(forcefield.rect.center().extend(0.0))
+            .with_scale(forcefield.rect.size().extend(1.0));
+        commands.spawn((
+            forcefield,
+            MaterialMesh2dBundle {
+                mesh: assets.square.clone(),
+                material: assets.green.clone(),
+                transform,
+                ..default()
+            }
+        ));
+    }
+}
+
+// One uniform field per line, `field <min x> <min y> <max x> <max y> <force
+// x> <force y>` followed by any of
+//   sine <frequency> <phase>, square <frequency> <duty>, ramp <duration>,
+//   keyframes <curve>, loop <curve>   strength waveform, constant without
+//   angle <curve>                     rotation in radians over time
+//   window <start> <end>              active interval, repeatable
+// where curves are written `t:value,t:value,...`.
+fn read_forcefields(forcefields: &str) -> Result<Vec<Forcefield>, String> {
+    forcefields
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| read_forcefield(line).map_err(|error| format!("{error} in `{line}`")))
+        .collect()
+}
+
+fn read_forcefield(line: &str) -> Result<Forcefield, String> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("field") {
+        return Err("expected a field".to_string());
+    }
+    let mut number = || read_number(words.next());
+    let rect = Rect::new(number()?, number()?, number()?, number()?);
+    let force = Vec2::new(number()?, number()?);
+
+    let mut modulation = Modulation::default();
+    while let Some(option) = words.next() {
+        let mut number = || read_number(words.next());
+        match option {
+            "sine" => modulation.strength = Waveform::Sine { frequency: number()?, phase: number()? },
+            "square" => modulation.strength = Waveform::Square { frequency: number()?, duty: number()? },
+            "ramp" => modulation.strength = Waveform::Ramp { duration: number()? },
+            "keyframes" | "loop" => {
+                let curve = read_curve(words.next().ok_or("missing curve")?)?;
+                modulation.strength = Waveform::Keyframes { curve, looping: option == "loop" };
+            }
+            "angle" => modulation.angle = Some(read_curve(words.next().ok_or("missing curve")?)?),
+            "window" => modulation.windows.push((number()?, number()?)),
+            _ => return Err(format!("unknown option `{option}`")),
+        }
+    }
+    Ok(Forcefield {
+        rect,
+        kind: ForcefieldKind::Uniform(force),
+        modulation,
+    })
+}
+
+fn read_number(word: Option<&str>) -> Result<f32, String> {
+    let word = word.ok_or("missing number")?;
+    word.parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("invalid number `{word}`"))
+}
+
+pub(crate) fn read_curve(curve: &str) -> Result<Curve, String> {
+    curve
+        .split(',')
+        .map(|keyframe| {
+            keyframe
+                .split_once(':')
+                .and_then(|(t, value)| Some((t.parse::<f32>().ok()?, value.parse::<f32>().ok()?)))
+                .filter(|(t, value)| t.is_finite() && value.is_finite())
+                .ok_or_else(|| format!("invalid keyframe `{keyframe}`"))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Curve::new)
+}
+
+fn sync_arena_noise(
+    mut commands: Commands,
+    arena_noise: Res<ArenaNoise>,
+    mut rng: ResMut<SimRng>,
+    q: Query<Entity, With<ArenaNoiseField>>,
+) {
+    if !arena_noise.is_changed() {
+        return;
+    }
+    for entity in q.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(noise) = arena_noise.0 else {
+        return;
+    };
+    commands.spawn((
+        Forcefield {
+            rect: Rect {
+                min: Vec2::splat(-BORDER_DISTANCE),
+                max: Vec2::splat(BORDER_DISTANCE),
+            },
+            kind: ForcefieldKind::Noise {
+                noise,
+                seed: rng.0.gen(),
+                scale: NOISE_SCALE,
+                strength: NOISE_STRENGTH,
+                evolution: NOISE_EVOLUTION,
+            },
+            modulation: Modulation::default()
+        },
+        ArenaNoiseField
+    ));
 }
 
-fn apply_forcefields_single_thread(
+pub(crate) fn apply_forcefields_single_thread(
     time: Res<Time>,
+    fields: Res<Assets<VectorField>>,
     q_forcefields: Query<&Forcefield>,
     mut q_particles: Query<(&mut Velocity, &Transform)>,
 ) {
+    let elapsed = time.elapsed_seconds();
     for forcefield in q_forcefields.iter() {
         for (mut velocity, transform) in q_particles.iter_mut() {
-            if forcefield.rect.contains(transform.translation.xy()) {
-                velocity.0 += forcefield.force * time.delta_seconds();
-            }
+            let force = forcefield.force_at(transform.translation.xy(), elapsed, &fields);
+            velocity.0 += force * time.delta_seconds();
         }
     }
 }
 
-fn apply_forcefields_parallel(
+pub(crate) fn apply_forcefields_parallel(
     time: Res<Time>,
+    fields: Res<Assets<VectorField>>,
     q_forcefields: Query<&Forcefield>,
     mut q_particles: Query<(&mut Velocity, &Transform)>,
 ) {
+    let elapsed = time.elapsed_seconds();
     q_particles.par_iter_mut().for_each(|(mut velocity, transform)| {
         for forcefield in q_forcefields.iter() {
-            if forcefield.rect.contains(transform.translation.xy()) {
-                velocity.0 += forcefield.force * time.delta_seconds();
-            }
+            let force = forcefield.force_at(transform.translation.xy(), elapsed, &fields);
+            velocity.0 += force * time.delta_seconds();
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn waveforms_sample_their_shape() {
+        assert_eq!(Waveform::Constant.sample(12.3), 1.0);
+
+        let sine = Waveform::Sine { frequency: 0.5, phase: 0.0 };
+        assert_close(sine.sample(0.0), 0.0);
+        assert_close(sine.sample(0.5), 1.0);
+        assert_close(sine.sample(1.5), -1.0);
+
+        let square = Waveform::Square { frequency: 1.0, duty: 0.25 };
+        assert_eq!(square.sample(0.1), 1.0);
+        assert_eq!(square.sample(0.5), -1.0);
+        assert_eq!(square.sample(1.2), 1.0);
+
+        let ramp = Waveform::Ramp { duration: 4.0 };
+        assert_eq!(ramp.sample(-1.0), 0.0);
+        assert_eq!(ramp.sample(1.0), 0.25);
+        assert_eq!(ramp.sample(10.0), 1.0);
+        assert_eq!(Waveform::Ramp { duration: 0.0 }.sample(0.0), 1.0);
+
+        let curve = Curve::new(vec![(0.0, 0.0), (2.0, 1.0)]);
+        let keyframes = Waveform::Keyframes { curve: curve.clone(), looping: false };
+        assert_eq!(keyframes.sample(1.0), 0.5);
+        assert_eq!(keyframes.sample(3.0), 1.0);
+        let looped = Waveform::Keyframes { curve, looping: true };
+        assert_eq!(looped.sample(3.0), 0.5);
+    }
+
+    #[test]
+    fn windows_switch_fields_on_and_off() {
+        let forcefield = Forcefield {
+            rect: Rect::new(-10.0, -10.0, 10.0, 10.0),
+            kind: ForcefieldKind::Uniform(Vec2::X),
+            modulation: Modulation {
+                windows: vec![(1.0, 2.0), (5.0, 6.0)],
+                ..default()
+            },
+        };
+        let fields = Assets::<VectorField>::default();
+        let force = |t: f32| forcefield.force_at(Vec2::ZERO, t, &fields);
+        assert_eq!(force(0.5), Vec2::ZERO);
+        assert_eq!(force(1.0), Vec2::X);
+        assert_eq!(force(2.0), Vec2::ZERO);
+        assert_eq!(force(5.5), Vec2::X);
+        assert_eq!(forcefield.force_at(Vec2::new(20.0, 0.0), 1.5, &fields), Vec2::ZERO);
+        assert!(Modulation::default().is_active(1e6));
+    }
+
+    #[test]
+    fn modulation_scales_and_rotates_forces() {
+        let modulation = Modulation {
+            strength: Waveform::Ramp { duration: 2.0 },
+            angle: Some(Curve::new(vec![(0.0, 0.0), (2.0, TAU / 4.0)])),
+            windows: Vec::new(),
+        };
+        let force = modulation.apply(Vec2::X, 2.0);
+        assert_close(force.x, 0.0);
+        assert_close(force.y, 1.0);
+        assert_eq!(modulation.apply(Vec2::X, 0.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn forcefield_files_parse() {
+        let forcefields = read_forcefields(include_str!("../presets/forcefields.preset")).unwrap();
+        assert_eq!(forcefields.len(), 2);
+        assert_eq!(forcefields[1].modulation.windows, vec![(0.0, 300.0)]);
+
+        let forcefield = read_forcefields("field 0 0 10 10 1 0 square 2 0.5 window 1 2 window 3 4").unwrap();
+        assert!(matches!(forcefield[0].modulation.strength, Waveform::Square { frequency: 2.0, duty: 0.5 }));
+        assert_eq!(forcefield[0].modulation.windows.len(), 2);
+        assert!(read_forcefields("field 0 0 10 10 1").is_err());
+        assert!(read_forcefields("field 0 0 10 10 1 0 ramp NaN").is_err());
+        assert!(read_forcefields("field 0 0 10 10 1 0 wobble 1").is_err());
+        assert!(read_forcefields("field 0 0 10 10 1 0 loop 0:1,x").is_err());
+    }
+}
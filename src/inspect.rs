@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+
+use bevy_pancam::PanCamSystemSet;
+
+use crate::{
+    particle::*,
+    physics::SimulationConfig,
+    trail::ShowTrail,
+    ui::CursorPosition,
+    tools::Tool,
+};
+
+pub struct InspectPlugin;
+
+impl Plugin for InspectPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Selection {
+            entity: None,
+            follow: false,
+        });
+        app.add_systems(Startup, spawn_inspect_panel);
+        app.add_systems(Update, (
+                select_particle.run_if(resource_equals(Tool::Select)),
+                update_inspect_panel,
+                draw_selection,
+                follow_selection.after(PanCamSystemSet))
+            .chain());
+    }
+}
+
+// The particle picked by clicking on it. While `follow` is set the camera
+// keeps it centred.
+#[derive(Resource)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+    pub follow: bool,
+}
+
+#[derive(Component)]
+pub struct Selected;
+
+#[derive(Component)]
+struct InspectText;
+
+// In screen pixels, scaled by the camera zoom.
+const PICK_RADIUS: f32 = 20.0;
+const HIGHLIGHT_RADIUS: f32 = 16.0;
+
+fn spawn_inspect_panel(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                ..default()
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            right: Val::Px(15.0),
+            ..default()
+        })
+        .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        InspectText
+    ));
+}
+
+// Left click selects the nearest particle under the cursor, F toggles
+// following it and Escape clears the selection.
+fn select_particle(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor: Res<CursorPosition>,
+    mut selection: ResMut<Selection>,
+    q_camera: Query<&OrthographicProjection>,
+    q: Query<(Entity, &Transform), With<Particle>>,
+) {
+    if selection.entity.is_some_and(|entity| q.get(entity).is_err()) {
+        selection.entity = None;
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        selection.follow = !selection.follow;
+    }
+
+    let previous = selection.entity;
+    if keys.just_pressed(KeyCode::Escape) {
+        selection.entity = None;
+    }
+    if let (true, Some(position), Ok(projection)) = (buttons.just_pressed(MouseButton::Left), cursor.0, q_camera.get_single()) {
+        let radius = PICK_RADIUS * projection.scale;
+        let nearest = q
+            .iter()
+            .map(|(entity, transform)| (entity, transform.translation.xy().distance(position)))
+            .filter(|(_, distance)| *distance < radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((entity, _)) = nearest {
+            selection.entity = Some(entity);
+        }
+    }
+
+    if selection.entity == previous {
+        return;
+    }
+    if let Some(entity) = previous.filter(|entity| q.contains(*entity)) {
+        commands.entity(entity).remove::<(Selected, ShowTrail)>();
+    }
+    if let Some(entity) = selection.entity {
+        commands.entity(entity).try_insert((Selected, ShowTrail));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_inspect_panel(
+    selection: Res<Selection>,
+    config: Res<SimulationConfig>,
+    mut q_text: Query<(&mut Text, &mut Visibility), With<InspectText>>,
+    q: Query<(Entity, &Transform, &Velocity, &Charge, &Species, &Acceleration, &Age), With<Particle>>,
+) {
+    let (mut text, mut visibility) = q_text.single_mut();
+    let Some((entity, transform, velocity, charge, species, acceleration, age)) = selection.entity.and_then(|entity| q.get(entity).ok()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+
+    let position = transform.translation.xy();
+    let neighbours = q
+        .iter()
+        .filter(|(other, other_transform, ..)| {
+            *other != entity && other_transform.translation.xy().distance(position) < config.interaction_radius
+        })
+        .count();
+    let follow = if selection.follow { "following" } else { "F to follow" };
+    text.sections[0].value = format!(
+        "Particle {entity} ({follow})\n\
+        Position: ({:.1}, {:.1})\n\
+        Velocity: ({:.1}, {:.1}) |{:.1}|\n\
+        Charge: {:.2}, species {}\n\
+        Acceleration (dv/dt): ({:.1}, {:.1}) |{:.1}|\n\
+        Neighbours: {neighbours}\n\
+        Age: {:.1}s",
+        position.x, position.y,
+        velocity.0.x, velocity.0.y, velocity.0.length(),
+        charge.0, species.0,
+        acceleration.value.x, acceleration.value.y, acceleration.value.length(),
+        age.0,
+    );
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    q_camera: Query<&OrthographicProjection>,
+    q: Query<&Transform, With<Particle>>,
+) {
+    let (Some(transform), Ok(projection)) = (selection.entity.and_then(|entity| q.get(entity).ok()), q_camera.get_single()) else {
+        return;
+    };
+    let radius = HIGHLIGHT_RADIUS.max(HIGHLIGHT_RADIUS * projection.scale);
+    gizmos.circle_2d(transform.translation.xy(), radius, Color::WHITE);
+}
+
+fn follow_selection(
+    selection: Res<Selection>,
+    mut q_camera: Query<(&mut Transform, &OrthographicProjection), Without<Particle>>,
+    q: Query<&Transform, With<Particle>>,
+) {
+    let (true, Some(transform)) = (selection.follow, selection.entity.and_then(|entity| q.get(entity).ok())) else {
+        return;
+    };
+    let Ok((mut camera, projection)) = q_camera.get_single_mut() else {
+        return;
+    };
+    // The viewport origin is the bottom left corner, so offset by half the
+    // visible area.
+    let target = transform.translation.xy() - projection.area.center();
+    camera.translation = target.extend(camera.translation.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn click(world: &mut World, position: Vec2) {
+        world.insert_resource(CursorPosition(Some(position)));
+        let mut buttons = ButtonInput::<MouseButton>::default();
+        buttons.press(MouseButton::Left);
+        world.insert_resource(buttons);
+        world.run_system_once(select_particle);
+    }
+
+    #[test]
+    fn clicks_pick_the_nearest_particle_in_reach() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.insert_resource(Selection { entity: None, follow: false });
+        world.spawn(OrthographicProjection { scale: 2.0, ..default() });
+        let far = spawn_test_particle(&mut world, Vec2::new(30.0, 0.0), Vec2::ZERO, 0, 1.0);
+        let near = spawn_test_particle(&mut world, Vec2::new(-10.0, 0.0), Vec2::ZERO, 0, 1.0);
+
+        // The pick radius grows with the zoom, 40 units here.
+        click(&mut world, Vec2::ZERO);
+        assert_eq!(world.resource::<Selection>().entity, Some(near));
+        assert!(world.get::<Selected>(near).is_some() && world.get::<ShowTrail>(near).is_some());
+
+        click(&mut world, Vec2::new(45.0, 0.0));
+        assert_eq!(world.resource::<Selection>().entity, Some(far));
+        assert!(world.get::<Selected>(near).is_none() && world.get::<ShowTrail>(near).is_none());
+
+        // Clicking on nothing keeps the selection.
+        click(&mut world, Vec2::new(500.0, 0.0));
+        assert_eq!(world.resource::<Selection>().entity, Some(far));
+    }
+
+    #[test]
+    fn despawned_particles_are_deselected() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.insert_resource(CursorPosition(None));
+        world.spawn(OrthographicProjection::default());
+        let entity = spawn_test_particle(&mut world, Vec2::ZERO, Vec2::ZERO, 0, 1.0);
+        world.insert_resource(Selection { entity: Some(entity), follow: true });
+
+        world.despawn(entity);
+        world.run_system_once(select_particle);
+        assert_eq!(world.resource::<Selection>().entity, None);
+    }
+}
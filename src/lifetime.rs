@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{
+    particle::*,
+    asset::ParticleAssets,
+    curve::Curve,
+};
+
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (increase_age, age_particles).chain());
+    }
+}
+
+// Particles with a lifetime are cancelled once their `Age` reaches
+// `duration`. The curves are sampled with the age as a fraction of the
+// lifetime: `charge` gives the charge directly and `colour` picks the
+// palette species the particle is drawn with.
+#[derive(Component, Clone)]
+pub struct Lifetime {
+    pub duration: f32,
+    pub fade: AgeFade,
+    pub charge: Option<Curve>,
+    pub colour: Option<Curve>,
+}
+
+#[derive(Clone, Copy)]
+pub enum AgeFade {
+    None,
+    Fade,
+    Shrink,
+}
+
+fn increase_age(
+    time: Res<Time>,
+    mut q: Query<&mut Age>,
+) {
+    q.par_iter_mut().for_each(|mut age| {
+        age.0 += time.delta_seconds();
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn age_particles(
+    assets: Res<ParticleAssets>,
+    mut q: Query<(
+        &Age,
+        &Lifetime,
+        &Species,
+        &mut Charge,
+        &mut Cancelled,
+        &mut Transform,
+        &mut ParticleColour,
+    )>,
+) {
+    for (age, lifetime, species, mut charge, mut cancelled, mut transform, mut particle_colour) in q.iter_mut() {
+        if age.0 >= lifetime.duration {
+            cancelled.0 = true;
+            continue;
+        }
+        let t = age.0 / lifetime.duration;
+
+        if let Some(curve) = &lifetime.charge {
+            charge.0 = curve.sample(t);
+        }
+        let colour = lifetime.colour
+            .as_ref()
+            .map_or(species.0, |curve| curve.sample(t).round().max(0.0) as usize);
+
+        let mut alpha = 1.0;
+        match lifetime.fade {
+            AgeFade::None => {}
+            AgeFade::Fade => alpha = 1.0 - t,
+            AgeFade::Shrink => transform.scale = Vec3::new(1.0 - t, 1.0 - t, 1.0),
+        }
+
+        let faded = assets.faded(colour, charge.0, alpha);
+        if particle_colour.0 != faded {
+            particle_colour.0 = faded;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lifetime(fade: AgeFade) -> Lifetime {
+        Lifetime { duration: 10.0, fade, charge: None, colour: None }
+    }
+
+    fn spawn(world: &mut World, age: f32, lifetime: Lifetime) -> Entity {
+        world.spawn((
+            Age(age),
+            lifetime,
+            Species(1),
+            Charge(1.0),
+            Cancelled(false),
+            Transform::default(),
+            ParticleColour(Handle::default()),
+        )).id()
+    }
+
+    fn age(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(age_particles);
+        schedule.run(world);
+    }
+
+    #[test]
+    fn particles_fade_or_shrink_with_age() {
+        let mut world = World::new();
+        let assets = ParticleAssets::placeholder();
+        let none = spawn(&mut world, 5.0, lifetime(AgeFade::None));
+        let fade = spawn(&mut world, 5.0, lifetime(AgeFade::Fade));
+        let shrink = spawn(&mut world, 5.0, lifetime(AgeFade::Shrink));
+        let expired = spawn(&mut world, 10.0, lifetime(AgeFade::Fade));
+        world.insert_resource(ParticleAssets::placeholder());
+        age(&mut world);
+
+        let colour = |world: &World, entity: Entity| world.get::<ParticleColour>(entity).unwrap().0.clone();
+        let scale = |world: &World, entity: Entity| world.get::<Transform>(entity).unwrap().scale;
+        assert_eq!(colour(&world, none), assets.material(1, 1.0));
+        assert_eq!(scale(&world, none), Vec3::ONE);
+        assert_eq!(colour(&world, fade), assets.faded(1, 1.0, 0.5));
+        assert_ne!(colour(&world, fade), assets.material(1, 1.0));
+        assert_eq!(colour(&world, shrink), assets.material(1, 1.0));
+        assert_eq!(scale(&world, shrink), Vec3::new(0.5, 0.5, 1.0));
+        assert!(!world.get::<Cancelled>(fade).unwrap().0);
+        assert!(world.get::<Cancelled>(expired).unwrap().0);
+    }
+
+    #[test]
+    fn curves_set_charge_and_colour_over_the_lifetime() {
+        let mut world = World::new();
+        let assets = ParticleAssets::placeholder();
+        let particle = spawn(&mut world, 2.5, Lifetime {
+            charge: Some(Curve::new(vec![(0.0, 1.0), (1.0, -1.0)])),
+            colour: Some(Curve::new(vec![(0.0, 0.0), (1.0, 4.0)])),
+            ..lifetime(AgeFade::None)
+        });
+        world.insert_resource(ParticleAssets::placeholder());
+        age(&mut world);
+        assert_eq!(world.get::<Charge>(particle).unwrap().0, 0.5);
+        assert_eq!(world.get::<ParticleColour>(particle).unwrap().0, assets.material(1, 0.5));
+
+        world.get_mut::<Age>(particle).unwrap().0 = 7.5;
+        age(&mut world);
+        assert_eq!(world.get::<Charge>(particle).unwrap().0, -0.5);
+        assert_eq!(world.get::<ParticleColour>(particle).unwrap().0, assets.material(3, -0.5));
+    }
+}
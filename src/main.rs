@@ -8,6 +8,26 @@ mod physics;
 mod emmiter;
 mod forcefield;
 mod collider;
+mod noise;
+mod curve;
+mod vectorfield;
+mod external;
+mod sink;
+mod rate;
+mod lifetime;
+mod reaction;
+mod bond;
+mod thermostat;
+mod colormap;
+mod trail;
+mod render;
+mod inspect;
+mod tools;
+mod panel;
+mod matrix;
+
+#[cfg(test)]
+mod parity;
 
 fn main() {
     App::new()
@@ -17,7 +37,7 @@ fn main() {
             ui::UIPlugin,
             asset::AssetPlugin,
             particle::ParticlePlugin,
-            physics::PhysicsPlugin { parallel: true }
+            physics::PhysicsPlugin { parallel: true, seed: 0 }
         ))
         .run();
 }
@@ -0,0 +1,327 @@
+use bevy::{
+    prelude::*,
+    ui::RelativeCursorPosition,
+};
+
+use std::fs;
+
+use crate::{
+    physics::{InteractionMatrix, SimRng},
+    colormap::ColourMap,
+    panel::{BlocksCursor, PRESET_DIRECTORY, PANEL_COLOUR, BUTTON_COLOUR},
+};
+
+pub struct MatrixEditorPlugin;
+
+impl Plugin for MatrixEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_matrix_editor);
+        app.add_systems(Update, (
+                toggle_matrix_editor,
+                drag_matrix_cells,
+                press_matrix_buttons,
+                update_matrix_editor)
+            .chain());
+    }
+}
+
+#[derive(Component)]
+struct MatrixEditor;
+
+#[derive(Component)]
+struct MatrixCell {
+    a: usize,
+    b: usize,
+}
+
+#[derive(Component)]
+struct MatrixCellText {
+    a: usize,
+    b: usize,
+}
+
+#[derive(Component, Clone, Copy)]
+enum MatrixButton {
+    Randomise,
+    Symmetrise,
+    Invert,
+    Clear,
+    Save,
+    Load,
+}
+
+#[derive(Component)]
+struct MatrixStatus;
+
+const CELL_SIZE: f32 = 36.0;
+const PRESET_PATH: &str = "presets/matrix.preset";
+
+fn spawn_matrix_editor(
+    mut commands: Commands,
+    matrix: Res<InteractionMatrix>,
+) {
+    let text = |value: String, font_size: f32| TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size,
+            ..default()
+        }
+    );
+    let label = |value: String| text(value, 16.0).with_style(Style {
+        width: Val::Px(CELL_SIZE),
+        height: Val::Px(CELL_SIZE),
+        ..default()
+    });
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(40.0),
+                    left: Val::Px(500.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(2.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: PANEL_COLOUR.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RelativeCursorPosition::default(),
+            BlocksCursor,
+            MatrixEditor
+        ))
+        .with_children(|panel| {
+            panel.spawn(text("Attraction of row to column (G to hide)".to_string(), 20.0));
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(2.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(label(String::new()));
+                    for b in 0..matrix.size {
+                        row.spawn(label(b.to_string()));
+                    }
+                });
+            for a in 0..matrix.size {
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(2.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(label(a.to_string()));
+                        for b in 0..matrix.size {
+                            row
+                                .spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(CELL_SIZE),
+                                            height: Val::Px(CELL_SIZE),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        ..default()
+                                    },
+                                    Interaction::default(),
+                                    RelativeCursorPosition::default(),
+                                    MatrixCell { a, b }
+                                ))
+                                .with_children(|cell| {
+                                    cell.spawn((text(String::new(), 12.0), MatrixCellText { a, b }));
+                                });
+                        }
+                    });
+            }
+
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(6.0),
+                        margin: UiRect::top(Val::Px(6.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (button, name) in [
+                        (MatrixButton::Randomise, "Randomise"),
+                        (MatrixButton::Symmetrise, "Symmetrise"),
+                        (MatrixButton::Invert, "Invert"),
+                        (MatrixButton::Clear, "Clear"),
+                        (MatrixButton::Save, "Save"),
+                        (MatrixButton::Load, "Load"),
+                    ] {
+                        row
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::all(Val::Px(4.0)),
+                                        ..default()
+                                    },
+                                    background_color: BUTTON_COLOUR.into(),
+                                    ..default()
+                                },
+                                button
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(text(name.to_string(), 20.0));
+                            });
+                    }
+                });
+
+            panel.spawn((text(String::new(), 20.0), MatrixStatus));
+        });
+}
+
+fn toggle_matrix_editor(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut q: Query<&mut Visibility, With<MatrixEditor>>,
+) {
+    if keys.just_pressed(KeyCode::KeyG) {
+        let mut visibility = q.single_mut();
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+// Pressing a cell sets it from the cursor height, +1 at the top and -1 at
+// the bottom, and keeps following it while dragging.
+fn drag_matrix_cells(
+    mut matrix: ResMut<InteractionMatrix>,
+    q: Query<(&MatrixCell, &Interaction, &RelativeCursorPosition)>,
+) {
+    for (cell, interaction, cursor) in q.iter() {
+        let (Interaction::Pressed, Some(position)) = (interaction, cursor.normalized) else {
+            continue;
+        };
+        let value = ((1.0 - 2.0 * position.y.clamp(0.0, 1.0)) * 10.0).round() / 10.0;
+        if matrix.get(cell.a, cell.b) != value {
+            matrix.set(cell.a, cell.b, value);
+        }
+    }
+}
+
+fn press_matrix_buttons(
+    mut matrix: ResMut<InteractionMatrix>,
+    mut rng: ResMut<SimRng>,
+    q_buttons: Query<(&MatrixButton, &Interaction), Changed<Interaction>>,
+    mut q_status: Query<&mut Text, With<MatrixStatus>>,
+) {
+    let mut status = q_status.single_mut();
+    for (button, interaction) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        status.sections[0].value = match button {
+            MatrixButton::Randomise => {
+                matrix.randomise(&mut rng);
+                String::new()
+            }
+            MatrixButton::Symmetrise => {
+                matrix.symmetrise();
+                String::new()
+            }
+            MatrixButton::Invert => {
+                matrix.invert();
+                String::new()
+            }
+            MatrixButton::Clear => {
+                matrix.values.fill(0.0);
+                String::new()
+            }
+            MatrixButton::Save => match fs::create_dir_all(PRESET_DIRECTORY)
+                .and_then(|_| fs::write(PRESET_PATH, write_matrix(&matrix)))
+            {
+                Ok(()) => format!("Saved {PRESET_PATH}"),
+                Err(error) => format!("Could not save {PRESET_PATH}: {error}"),
+            },
+            MatrixButton::Load => match fs::read_to_string(PRESET_PATH)
+                .map_err(|error| error.to_string())
+                .and_then(|preset| read_matrix(&preset, matrix.size))
+            {
+                Ok(values) => {
+                    matrix.values = values;
+                    format!("Loaded {PRESET_PATH}")
+                }
+                Err(error) => format!("Could not load {PRESET_PATH}: {error}"),
+            },
+        };
+    }
+}
+
+fn update_matrix_editor(
+    matrix: Res<InteractionMatrix>,
+    mut q_cells: Query<(&MatrixCell, &mut BackgroundColor)>,
+    mut q_text: Query<(&MatrixCellText, &mut Text)>,
+) {
+    if !matrix.is_changed() {
+        return;
+    }
+    for (cell, mut colour) in q_cells.iter_mut() {
+        let value = matrix.get(cell.a, cell.b);
+        colour.0 = ColourMap::Diverging.sample(0.5 + 0.5 * value);
+    }
+    for (cell, mut text) in q_text.iter_mut() {
+        text.sections[0].value = format!("{:.1}", matrix.get(cell.a, cell.b));
+    }
+}
+
+// One row of whitespace separated values per line.
+fn write_matrix(matrix: &InteractionMatrix) -> String {
+    matrix
+        .values
+        .chunks(matrix.size)
+        .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ") + "\n")
+        .collect()
+}
+
+fn read_matrix(preset: &str, size: usize) -> Result<Vec<f32>, String> {
+    let rows = preset
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split_whitespace()
+                .map(|value| value.parse::<f32>().ok().filter(|value| value.is_finite()))
+                .map(|value| value.map(|value| value.clamp(-1.0, 1.0)))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("invalid row `{line}`"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if rows.len() != size || rows.iter().any(|row| row.len() != size) {
+        return Err(format!("expected a {size}x{size} matrix"));
+    }
+    Ok(rows.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_presets_round_trip() {
+        let mut matrix = InteractionMatrix::default();
+        matrix.set(0, 1, 0.5);
+        matrix.set(1, 0, -0.25);
+        matrix.set(2, 2, 1.0);
+        assert_eq!(read_matrix(&write_matrix(&matrix), matrix.size), Ok(matrix.values.clone()));
+        assert!(read_matrix("0 1\n1 0\n", matrix.size).is_err());
+
+        let mut preset = write_matrix(&InteractionMatrix::default());
+        preset.replace_range(0..1, "NaN");
+        assert!(read_matrix(&preset, matrix.size).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+// Gradient noise over (x, y, t). Gradients are picked by hashing the lattice
+// corner with the seed, so no permutation table has to be stored.
+pub fn perlin(seed: u32, p: Vec3) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let u = Vec3::new(fade(f.x), fade(f.y), fade(f.z));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let gradient = gradient(hash(seed, x + dx, y + dy, z + dz));
+        gradient.dot(f - Vec3::new(dx as f32, dy as f32, dz as f32))
+    };
+
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u.x);
+    let y0 = lerp(x00, x10, u.y);
+    let y1 = lerp(x01, x11, u.y);
+    lerp(y0, y1, u.z)
+}
+
+// Simplex noise over (x, y, t), sharing the gradients and hash with
+// `perlin`. Cheaper than Perlin noise and without its axis aligned artefacts.
+pub fn simplex(seed: u32, p: Vec3) -> f32 {
+    const F3: f32 = 1.0 / 3.0;
+    const G3: f32 = 1.0 / 6.0;
+    // Skew into the simplex grid to find the cell, then unskew the offset.
+    let cell = (p + Vec3::splat((p.x + p.y + p.z) * F3)).floor();
+    let f = p - (cell - Vec3::splat((cell.x + cell.y + cell.z) * G3));
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+
+    // The two middle corners of the simplex depend on the order of the
+    // offset components.
+    let (first, second) = if f.x >= f.y {
+        if f.y >= f.z {
+            (IVec3::X, IVec3::new(1, 1, 0))
+        } else if f.x >= f.z {
+            (IVec3::X, IVec3::new(1, 0, 1))
+        } else {
+            (IVec3::Z, IVec3::new(1, 0, 1))
+        }
+    } else if f.y < f.z {
+        (IVec3::Z, IVec3::new(0, 1, 1))
+    } else if f.x < f.z {
+        (IVec3::Y, IVec3::new(0, 1, 1))
+    } else {
+        (IVec3::Y, IVec3::new(1, 1, 0))
+    };
+
+    let corner = |offset: IVec3, n: f32| {
+        let d = f - offset.as_vec3() + Vec3::splat(n * G3);
+        let t = 0.6 - d.length_squared();
+        if t <= 0.0 {
+            return 0.0;
+        }
+        let gradient = gradient(hash(seed, x + offset.x, y + offset.y, z + offset.z));
+        t * t * t * t * gradient.dot(d)
+    };
+
+    32.0 * (corner(IVec3::ZERO, 0.0) + corner(first, 1.0) + corner(second, 2.0) + corner(IVec3::ONE, 3.0))
+}
+
+// Divergence free field from the curl of a scalar noise potential, so
+// particles get swirled around instead of being pushed into sinks.
+pub fn curl(seed: u32, p: Vec2, t: f32) -> Vec2 {
+    const EPSILON: f32 = 0.01;
+    let dx = perlin(seed, Vec3::new(p.x + EPSILON, p.y, t))
+        - perlin(seed, Vec3::new(p.x - EPSILON, p.y, t));
+    let dy = perlin(seed, Vec3::new(p.x, p.y + EPSILON, t))
+        - perlin(seed, Vec3::new(p.x, p.y - EPSILON, t));
+    Vec2::new(dy, -dx) / (2.0 * EPSILON)
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn hash(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^ (h >> 16)
+}
+
+fn gradient(hash: u32) -> Vec3 {
+    match hash % 12 {
+        0 => Vec3::new(1.0, 1.0, 0.0),
+        1 => Vec3::new(-1.0, 1.0, 0.0),
+        2 => Vec3::new(1.0, -1.0, 0.0),
+        3 => Vec3::new(-1.0, -1.0, 0.0),
+        4 => Vec3::new(1.0, 0.0, 1.0),
+        5 => Vec3::new(-1.0, 0.0, 1.0),
+        6 => Vec3::new(1.0, 0.0, -1.0),
+        7 => Vec3::new(-1.0, 0.0, -1.0),
+        8 => Vec3::new(0.0, 1.0, 1.0),
+        9 => Vec3::new(0.0, -1.0, 1.0),
+        10 => Vec3::new(0.0, 1.0, -1.0),
+        _ => Vec3::new(0.0, -1.0, -1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = Vec3> {
+        (0..200).map(|i| {
+            let i = i as f32;
+            Vec3::new(i * 0.037 - 3.0, i * 0.061 - 5.0, i * 0.013)
+        })
+    }
+
+    #[test]
+    fn noise_is_deterministic_per_seed() {
+        for p in points() {
+            assert_eq!(perlin(7, p), perlin(7, p));
+            assert_eq!(simplex(7, p), simplex(7, p));
+            assert_eq!(curl(7, p.xy(), p.z), curl(7, p.xy(), p.z));
+        }
+        assert!(points().any(|p| perlin(7, p) != perlin(8, p)));
+        assert!(points().any(|p| simplex(7, p) != simplex(8, p)));
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        for p in points() {
+            assert!(perlin(3, p).abs() <= 1.5);
+            assert!(simplex(3, p).abs() <= 1.5);
+        }
+        // Lattice points are zero for gradient noise.
+        assert_eq!(perlin(3, Vec3::new(2.0, -4.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn curl_has_no_divergence() {
+        // Central differences with the step `curl` uses itself, for which the
+        // divergence cancels up to rounding.
+        const H: f32 = 0.01;
+        let divergence = |seed: u32, p: Vec2, t: f32, field: &dyn Fn(u32, Vec2, f32) -> Vec2| {
+            (field(seed, p + Vec2::X * H, t).x - field(seed, p - Vec2::X * H, t).x
+                + field(seed, p + Vec2::Y * H, t).y - field(seed, p - Vec2::Y * H, t).y)
+                / (2.0 * H)
+        };
+        let noise_pair = |seed: u32, p: Vec2, t: f32| {
+            Vec2::new(perlin(seed, p.extend(t)), perlin(seed.wrapping_add(1), p.extend(t)))
+        };
+
+        let mut reference = 0.0_f32;
+        for p in points() {
+            let (p, t) = (p.xy(), p.z);
+            let curl_divergence = divergence(5, p, t, &curl);
+            assert!(curl_divergence.abs() < 0.02, "divergence {curl_divergence} at {p}");
+            reference = reference.max(divergence(5, p, t, &noise_pair).abs());
+        }
+        // A field made of two independent noise values does diverge.
+        assert!(reference > 1.0);
+    }
+}
@@ -0,0 +1,475 @@
+use bevy::{
+    prelude::*,
+    ui::{RelativeCursorPosition, UiSystem},
+};
+
+use bevy_pancam::PanCam;
+
+use std::fs;
+
+use crate::{
+    physics::{SimulationConfig, BoundaryMode},
+    ui::{CursorPosition, update_cursor_position},
+};
+
+pub struct ParameterPanelPlugin;
+
+impl Plugin for ParameterPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_parameter_panel);
+        app.add_systems(PreUpdate, block_cursor_over_panels
+            .after(update_cursor_position)
+            .after(UiSystem::Focus));
+        app.add_systems(Update, (
+                toggle_parameter_panel,
+                drag_sliders,
+                press_panel_buttons,
+                update_parameter_panel)
+            .chain());
+    }
+}
+
+// UI nodes that should swallow the cursor, so clicks and drags on them don't
+// reach the tools or pan the camera.
+#[derive(Component)]
+pub struct BlocksCursor;
+
+#[derive(Component)]
+struct ParameterPanel;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Parameter {
+    ForceConstant,
+    InteractionRadius,
+    Damping,
+    MaxSpeed,
+    EmissionRate,
+    DeletionRadius,
+    TimeScale,
+    GravityX,
+    GravityY,
+    ElectricFieldX,
+    ElectricFieldY,
+    MagneticField,
+}
+
+const PARAMETERS: [Parameter; 12] = [
+    Parameter::ForceConstant,
+    Parameter::InteractionRadius,
+    Parameter::Damping,
+    Parameter::MaxSpeed,
+    Parameter::EmissionRate,
+    Parameter::DeletionRadius,
+    Parameter::TimeScale,
+    Parameter::GravityX,
+    Parameter::GravityY,
+    Parameter::ElectricFieldX,
+    Parameter::ElectricFieldY,
+    Parameter::MagneticField,
+];
+
+impl Parameter {
+    fn name(&self) -> &'static str {
+        match self {
+            Parameter::ForceConstant => "Force constant",
+            Parameter::InteractionRadius => "Interaction radius",
+            Parameter::Damping => "Damping",
+            Parameter::MaxSpeed => "Max speed",
+            Parameter::EmissionRate => "Emitter rate",
+            Parameter::DeletionRadius => "Deletion radius",
+            Parameter::TimeScale => "Time scale",
+            Parameter::GravityX => "Gravity x",
+            Parameter::GravityY => "Gravity y",
+            Parameter::ElectricFieldX => "Electric field x",
+            Parameter::ElectricFieldY => "Electric field y",
+            Parameter::MagneticField => "Magnetic field",
+        }
+    }
+
+    fn range(&self) -> (f32, f32) {
+        match self {
+            Parameter::ForceConstant => (0.0, 5_000_000.0),
+            Parameter::InteractionRadius => (10.0, 2000.0),
+            Parameter::Damping => (0.9, 1.0),
+            Parameter::MaxSpeed => (10.0, 5000.0),
+            Parameter::EmissionRate => (0.1, 100.0),
+            Parameter::DeletionRadius => (0.0, 100.0),
+            Parameter::TimeScale => (0.0, 4.0),
+            Parameter::GravityX | Parameter::GravityY => (-1000.0, 1000.0),
+            Parameter::ElectricFieldX | Parameter::ElectricFieldY => (-1000.0, 1000.0),
+            Parameter::MagneticField => (-5.0, 5.0),
+        }
+    }
+
+    fn value<'a>(&self, config: &'a mut SimulationConfig) -> &'a mut f32 {
+        match self {
+            Parameter::ForceConstant => &mut config.force_constant,
+            Parameter::InteractionRadius => &mut config.interaction_radius,
+            Parameter::Damping => &mut config.damping,
+            Parameter::MaxSpeed => &mut config.max_speed,
+            Parameter::EmissionRate => &mut config.emission_rate,
+            Parameter::DeletionRadius => &mut config.deletion_radius,
+            Parameter::TimeScale => &mut config.time_scale,
+            Parameter::GravityX => &mut config.gravity.x,
+            Parameter::GravityY => &mut config.gravity.y,
+            Parameter::ElectricFieldX => &mut config.electric_field.x,
+            Parameter::ElectricFieldY => &mut config.electric_field.y,
+            Parameter::MagneticField => &mut config.magnetic_field,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Slider(Parameter);
+
+#[derive(Component)]
+struct SliderFill(Parameter);
+
+#[derive(Component)]
+struct SliderValue(Parameter);
+
+#[derive(Component, Clone, Copy)]
+enum PanelButton {
+    Boundary,
+    Reset,
+    Save,
+    Load,
+}
+
+#[derive(Component)]
+struct BoundaryText;
+
+#[derive(Component)]
+struct PanelStatus;
+
+pub(crate) const PRESET_DIRECTORY: &str = "presets";
+const PRESET_PATH: &str = "presets/simulation.preset";
+pub(crate) const PANEL_COLOUR: Color = Color::srgba(0.0, 0.0, 0.0, 0.7);
+const TRACK_COLOUR: Color = Color::srgb(0.25, 0.25, 0.25);
+const FILL_COLOUR: Color = Color::srgb(0.6, 0.6, 0.9);
+pub(crate) const BUTTON_COLOUR: Color = Color::srgb(0.2, 0.2, 0.3);
+
+fn spawn_parameter_panel(
+    mut commands: Commands,
+) {
+    let text = |value: &str| TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 20.0,
+            ..default()
+        }
+    );
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(40.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: PANEL_COLOUR.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RelativeCursorPosition::default(),
+            BlocksCursor,
+            ParameterPanel
+        ))
+        .with_children(|panel| {
+            panel.spawn(text("Parameters (P to hide)"));
+            for parameter in PARAMETERS {
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(10.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn(text(parameter.name()).with_style(Style {
+                            width: Val::Px(170.0),
+                            ..default()
+                        }));
+                        row
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Px(200.0),
+                                        height: Val::Px(16.0),
+                                        ..default()
+                                    },
+                                    background_color: TRACK_COLOUR.into(),
+                                    ..default()
+                                },
+                                Interaction::default(),
+                                RelativeCursorPosition::default(),
+                                Slider(parameter)
+                            ))
+                            .with_children(|track| {
+                                track.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            height: Val::Percent(100.0),
+                                            ..default()
+                                        },
+                                        background_color: FILL_COLOUR.into(),
+                                        ..default()
+                                    },
+                                    SliderFill(parameter)
+                                ));
+                            });
+                        row.spawn((text(""), SliderValue(parameter)));
+                    });
+            }
+
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(6.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (button, label) in [
+                        (PanelButton::Boundary, ""),
+                        (PanelButton::Reset, "Reset to defaults"),
+                        (PanelButton::Save, "Save as preset"),
+                        (PanelButton::Load, "Load preset"),
+                    ] {
+                        row
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        padding: UiRect::all(Val::Px(4.0)),
+                                        ..default()
+                                    },
+                                    background_color: BUTTON_COLOUR.into(),
+                                    ..default()
+                                },
+                                button
+                            ))
+                            .with_children(|parent| {
+                                let mut label = parent.spawn(text(label));
+                                if matches!(button, PanelButton::Boundary) {
+                                    label.insert(BoundaryText);
+                                }
+                            });
+                    }
+                });
+
+            panel.spawn((text(""), PanelStatus));
+        });
+}
+
+fn block_cursor_over_panels(
+    mut cursor: ResMut<CursorPosition>,
+    q_panels: Query<(&RelativeCursorPosition, &InheritedVisibility), With<BlocksCursor>>,
+    mut q_camera: Query<&mut PanCam>,
+) {
+    let blocked = q_panels
+        .iter()
+        .any(|(position, visibility)| visibility.get() && position.mouse_over());
+    if blocked {
+        cursor.0 = None;
+    }
+    for mut pancam in q_camera.iter_mut() {
+        if pancam.enabled == blocked {
+            pancam.enabled = !blocked;
+        }
+    }
+}
+
+fn toggle_parameter_panel(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut q: Query<&mut Visibility, With<ParameterPanel>>,
+) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        let mut visibility = q.single_mut();
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn drag_sliders(
+    mut config: ResMut<SimulationConfig>,
+    q: Query<(&Slider, &Interaction, &RelativeCursorPosition)>,
+) {
+    for (slider, interaction, cursor) in q.iter() {
+        let (Interaction::Pressed, Some(position)) = (interaction, cursor.normalized) else {
+            continue;
+        };
+        let (min, max) = slider.0.range();
+        let value = min + position.x.clamp(0.0, 1.0) * (max - min);
+        if *slider.0.value(config.bypass_change_detection()) != value {
+            *slider.0.value(&mut config) = value;
+        }
+    }
+}
+
+fn press_panel_buttons(
+    mut config: ResMut<SimulationConfig>,
+    q_buttons: Query<(&PanelButton, &Interaction), Changed<Interaction>>,
+    mut q_status: Query<&mut Text, With<PanelStatus>>,
+) {
+    let mut status = q_status.single_mut();
+    for (button, interaction) in q_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        status.sections[0].value = match button {
+            PanelButton::Boundary => {
+                config.boundary = config.boundary.next();
+                String::new()
+            }
+            PanelButton::Reset => {
+                *config = SimulationConfig::default();
+                "Reset to defaults".to_string()
+            }
+            PanelButton::Save => match fs::create_dir_all(PRESET_DIRECTORY)
+                .and_then(|_| fs::write(PRESET_PATH, write_preset(&config)))
+            {
+                Ok(()) => format!("Saved {PRESET_PATH}"),
+                Err(error) => format!("Could not save {PRESET_PATH}: {error}"),
+            },
+            PanelButton::Load => match fs::read_to_string(PRESET_PATH)
+                .map_err(|error| error.to_string())
+                .and_then(|preset| read_preset(&preset))
+            {
+                Ok(preset) => {
+                    *config = preset;
+                    format!("Loaded {PRESET_PATH}")
+                }
+                Err(error) => format!("Could not load {PRESET_PATH}: {error}"),
+            },
+        };
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_parameter_panel(
+    mut config: ResMut<SimulationConfig>,
+    mut q_fills: Query<(&SliderFill, &mut Style)>,
+    mut q_values: Query<(&SliderValue, &mut Text), Without<BoundaryText>>,
+    mut q_boundary: Query<&mut Text, With<BoundaryText>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    let config = config.bypass_change_detection();
+    for (fill, mut style) in q_fills.iter_mut() {
+        let (min, max) = fill.0.range();
+        let t = (*fill.0.value(config) - min) / (max - min);
+        style.width = Val::Percent(t.clamp(0.0, 1.0) * 100.0);
+    }
+    for (value, mut text) in q_values.iter_mut() {
+        let value = *value.0.value(config);
+        text.sections[0].value = if value.abs() >= 1000.0 {
+            format!("{value:.0}")
+        } else {
+            format!("{value:.3}")
+        };
+    }
+    q_boundary.single_mut().sections[0].value = format!("Boundary: {}", config.boundary.name());
+}
+
+// One `name = value` pair per line.
+fn write_preset(config: &SimulationConfig) -> String {
+    let mut preset = String::new();
+    let mut config = config.clone();
+    for parameter in PARAMETERS {
+        preset += &format!("{} = {}\n", preset_key(parameter), parameter.value(&mut config));
+    }
+    preset += &format!("boundary = {}\n", config.boundary.name());
+    preset
+}
+
+fn read_preset(preset: &str) -> Result<SimulationConfig, String> {
+    let mut config = SimulationConfig::default();
+    for line in preset.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| format!("expected `name = value`, got `{line}`"))?;
+        if key == "boundary" {
+            config.boundary = [BoundaryMode::Reflect, BoundaryMode::Wrap, BoundaryMode::Open]
+                .into_iter()
+                .find(|mode| mode.name() == value)
+                .ok_or_else(|| format!("unknown boundary mode `{value}`"))?;
+            continue;
+        }
+        let parameter = PARAMETERS
+            .into_iter()
+            .find(|parameter| preset_key(*parameter) == key)
+            .ok_or_else(|| format!("unknown parameter `{key}`"))?;
+        let value = value
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid value `{value}` for `{key}`"))?;
+        // Hand edited presets are clamped to the slider ranges, which keep
+        // the emitter period and time scale valid.
+        let (min, max) = parameter.range();
+        *parameter.value(&mut config) = value.clamp(min, max);
+    }
+    Ok(config)
+}
+
+fn preset_key(parameter: Parameter) -> &'static str {
+    match parameter {
+        Parameter::ForceConstant => "force_constant",
+        Parameter::InteractionRadius => "interaction_radius",
+        Parameter::Damping => "damping",
+        Parameter::MaxSpeed => "max_speed",
+        Parameter::EmissionRate => "emission_rate",
+        Parameter::DeletionRadius => "deletion_radius",
+        Parameter::TimeScale => "time_scale",
+        Parameter::GravityX => "gravity_x",
+        Parameter::GravityY => "gravity_y",
+        Parameter::ElectricFieldX => "electric_field_x",
+        Parameter::ElectricFieldY => "electric_field_y",
+        Parameter::MagneticField => "magnetic_field",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_round_trip() {
+        let config = SimulationConfig {
+            force_constant: 250_000.0,
+            damping: 0.95,
+            boundary: BoundaryMode::Wrap,
+            time_scale: 0.5,
+            gravity: Vec2::new(0.0, -100.0),
+            magnetic_field: 1.5,
+            ..default()
+        };
+        assert!(read_preset(&write_preset(&config)) == Ok(config));
+        assert!(read_preset("damping = fast").is_err());
+        assert!(read_preset("gravity = 1").is_err());
+        assert!(read_preset("gravity_y = -100").unwrap().gravity == Vec2::new(0.0, -100.0));
+    }
+
+    #[test]
+    fn bad_presets_are_rejected_or_clamped() {
+        assert!(read_preset("emission_rate = NaN").is_err());
+        assert!(read_preset("time_scale = inf").is_err());
+
+        let config = read_preset("emission_rate = 0\ntime_scale = -2\ndamping = 7").unwrap();
+        assert_eq!(config.emission_rate, Parameter::EmissionRate.range().0);
+        assert_eq!(config.time_scale, 0.0);
+        assert_eq!(config.damping, 1.0);
+    }
+}
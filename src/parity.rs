@@ -0,0 +1,135 @@
+use bevy::{
+    prelude::*,
+    ecs::schedule::SystemConfigs,
+    math::bounding::Aabb2d,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+
+use std::time::Duration;
+
+use crate:: {
+    particle::*,
+    physics::*,
+    forcefield::*,
+    collider::*,
+    vectorfield::VectorField,
+};
+
+const SEED: u64 = 0x5eed;
+const PARTICLES: usize = 200;
+const TICKS: usize = 30;
+const TICK: Duration = Duration::from_micros(16_667);
+const TOLERANCE: f32 = 1e-3;
+
+fn seeded_world() -> World {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    let mut world = World::new();
+    world.insert_resource(Time::<()>::default());
+    world.insert_resource(Assets::<VectorField>::default());
+    world.insert_resource(SimulationConfig::default());
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut matrix = InteractionMatrix::default();
+    matrix.randomise(&mut SimRng(StdRng::seed_from_u64(SEED)));
+    world.insert_resource(matrix);
+
+    for _i in 0..PARTICLES {
+        let position = Vec2::new(rng.gen_range(-1000.0..1000.0), rng.gen_range(-1000.0..1000.0));
+        let velocity = Vec2::new(rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+        let charge = if rng.gen::<bool>() { 1.0 } else { -1.0 };
+        spawn_test_particle(&mut world, position, velocity, rng.gen_range(0..4), charge);
+    }
+
+    // Pairs on top of each other, neutral like annihilation photons and
+    // charged.
+    for charge in [0.0, 1.0, -1.0] {
+        for _pair in 0..2 {
+            spawn_test_particle(&mut world, Vec2::new(700.0, charge * 300.0), Vec2::ZERO, 0, charge);
+        }
+    }
+
+    world.spawn(Forcefield {
+        rect: Rect::new(-500.0, -500.0, 500.0, 500.0),
+        kind: ForcefieldKind::Uniform(Vec2::new(500.0, -250.0)),
+        modulation: Modulation::default(),
+    });
+    world.spawn(Forcefield {
+        rect: Rect::new(-1000.0, -1000.0, 1000.0, 1000.0),
+        kind: ForcefieldKind::Noise {
+            noise: NoiseType::Curl,
+            seed: SEED as u32,
+            scale: 500.0,
+            strength: 200.0,
+            evolution: 0.5,
+        },
+        modulation: Modulation::default(),
+    });
+    world.spawn(Collider {
+        aabb: Aabb2d { min: Vec2::new(-200.0, -800.0), max: Vec2::new(200.0, 800.0) },
+    });
+
+    world
+}
+
+fn run(systems: SystemConfigs) -> Vec<(Vec2, Vec2)> {
+    let mut world = seeded_world();
+    let mut schedule = Schedule::default();
+    schedule.add_systems((systems, apply_particle_velocities).chain());
+
+    for _tick in 0..TICKS {
+        world.resource_mut::<Time>().advance_by(TICK);
+        schedule.run(&mut world);
+    }
+
+    let mut state = world
+        .query::<(Entity, &Velocity, &Transform)>()
+        .iter(&world)
+        .map(|(entity, velocity, transform)| (entity, velocity.0, transform.translation.xy()))
+        .collect::<Vec<_>>();
+    state.sort_by_key(|(entity, _, _)| *entity);
+    state.into_iter().map(|(_, velocity, position)| (velocity, position)).collect()
+}
+
+fn assert_parity(parallel: SystemConfigs, single: SystemConfigs) {
+    let parallel = run(parallel);
+    let single = run(single);
+    assert_eq!(parallel.len(), single.len());
+    for (i, ((v_a, p_a), (v_b, p_b))) in parallel.iter().zip(single.iter()).enumerate() {
+        assert!(v_a.is_finite() && v_b.is_finite(), "particle {i} has velocity {v_a} and {v_b}");
+        let scale = 1.0 + v_a.length().max(p_a.length());
+        assert!(
+            v_a.distance(*v_b) <= TOLERANCE * scale && p_a.distance(*p_b) <= TOLERANCE * scale,
+            "particle {i} diverged: parallel {v_a} @ {p_a}, single threaded {v_b} @ {p_b}"
+        );
+    }
+}
+
+#[test]
+fn particle_forces_parity() {
+    assert_parity(
+        apply_particle_forces_parallel.into_configs(),
+        apply_particle_forces_combination.into_configs(),
+    );
+}
+
+#[test]
+fn forcefields_parity() {
+    assert_parity(
+        apply_forcefields_parallel.into_configs(),
+        apply_forcefields_single_thread.into_configs(),
+    );
+}
+
+#[test]
+fn collisions_parity() {
+    assert_parity(
+        handle_collisions_parallel.into_configs(),
+        handle_collisions_single_threaded.into_configs(),
+    );
+}
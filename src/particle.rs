@@ -1,25 +1,46 @@
 
 use bevy:: {
     prelude::*,
-    sprite::MaterialMesh2dBundle,
+    ecs::{system::EntityCommands, world::DeferredWorld},
+    utils::HashMap,
 };
 use rand::random;
 
+use std::f32::consts::TAU;
+
 use crate::{
     asset::ParticleAssets,
     emmiter::EmmiterPlugin,
+    sink::SinkPlugin,
+    lifetime::{LifetimePlugin, Lifetime, AgeFade},
+    reaction::{ReactionPlugin, apply_reactions},
+    rate::WindowedRate,
 };
 
 pub struct ParticlePlugin;
 
 impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ParticleCounter(0));
+        app.insert_resource(ParticleCounts::default());
+        register_particle_count_hooks(app.world_mut());
+        app.insert_resource(AnnihilationConfig {
+            photons: PHOTON_COUNT,
+            photon_lifetime: PHOTON_LIFETIME,
+            impulse: IMPULSE_STRENGTH,
+            impulse_radius: IMPULSE_RADIUS,
+        });
+        app.insert_resource(AnnihilationRate::default());
+        app.add_event::<AnnihilationEvent>();
         app.add_plugins(EmmiterPlugin);
+        app.add_plugins(SinkPlugin);
+        app.add_plugins(LifetimePlugin);
+        app.add_plugins(ReactionPlugin);
         app.add_systems(Update, (
-                cancel_collided_particles,
+                apply_reactions,
+                (release_annihilation_energy, update_annihilation_rate),
                 delete_cancelled_particles
-            ));
+            ).chain());
+        app.add_systems(PostUpdate, recount_changed_particles);
     }
 }
 
@@ -29,80 +50,339 @@ pub struct Velocity(pub Vec2);
 #[derive(Component)]
 pub struct Charge(pub f32);
 
+#[derive(Component)]
+pub struct Species(pub usize);
+
 #[derive(Component)]
 pub struct Cancelled(pub bool);
 
+// Seconds since the particle was spawned.
+#[derive(Component)]
+pub struct Age(pub f32);
+
+// Acceleration over the frame, measured from the change in velocity since
+// `previous` was recorded at the start of it. This is everything that changed
+// the velocity, so damping, the thermostat and wall reflections show up in it
+// along with the forces. New particles start from their spawn velocity so
+// their first frame doesn't read as v/dt.
+#[derive(Component, Default)]
+pub struct Acceleration {
+    pub value: Vec2,
+    pub previous: Vec2,
+}
+
 #[derive(Component)]
 pub struct Particle;
 
+// Palette or gradient entry the particle is drawn with. The render mode
+// decides whether it becomes a mesh material or an instance colour.
+#[derive(Component, Clone, PartialEq)]
+pub struct ParticleColour(pub Handle<ColorMaterial>);
+
 #[derive(Bundle)]
 pub struct ParticleBundle {
     pub velocity: Velocity,
     pub charge: Charge,
+    pub species: Species,
     pub cancelled: Cancelled,
+    pub age: Age,
+    pub acceleration: Acceleration,
     pub particle: Particle
 }
 
+// Spawns a particle with everything it needs to move and be drawn. Callers
+// add anything else, like a `Lifetime`, to the returned entity.
+pub fn spawn_particle<'a>(
+    commands: &'a mut Commands,
+    assets: &ParticleAssets,
+    position: Vec2,
+    velocity: Vec2,
+    species: usize,
+    charge: f32,
+) -> EntityCommands<'a> {
+    commands.spawn((
+        ParticleBundle {
+            velocity: Velocity(velocity),
+            charge: Charge(charge),
+            species: Species(species),
+            cancelled: Cancelled(false),
+            age: Age(0.0),
+            acceleration: Acceleration { value: Vec2::ZERO, previous: velocity },
+            particle: Particle
+        },
+        SpatialBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+        ParticleColour(assets.material(species, charge)),
+    ))
+}
+
+// Spawns a particle straight into `world`, for tests that run systems on a
+// bare `World` rather than an `App`.
+#[cfg(test)]
+pub fn spawn_test_particle(world: &mut World, position: Vec2, velocity: Vec2, species: usize, charge: f32) -> Entity {
+    let mut queue = bevy::ecs::world::CommandQueue::default();
+    let entity = spawn_particle(
+        &mut Commands::new(&mut queue, world),
+        &ParticleAssets::placeholder(),
+        position,
+        velocity,
+        species,
+        charge,
+    ).id();
+    queue.apply(world);
+    entity
+}
+
+// Live particle counts, kept in sync by the `Particle` add and remove hooks
+// so they can't drift from the ECS however particles are despawned. Each
+// particle is remembered with the species and charge sign it was counted
+// under, which is what gets subtracted again when it goes away.
+#[derive(Resource, Default)]
+pub struct ParticleCounts {
+    pub total: usize,
+    pub positive: usize,
+    pub negative: usize,
+    pub neutral: usize,
+    pub species: HashMap<usize, usize>,
+    tracked: HashMap<Entity, (usize, f32)>,
+}
+
+impl ParticleCounts {
+    fn sign_count(&mut self, charge: f32) -> &mut usize {
+        if charge > 0.0 {
+            &mut self.positive
+        } else if charge < 0.0 {
+            &mut self.negative
+        } else {
+            &mut self.neutral
+        }
+    }
+
+    fn add(&mut self, entity: Entity, species: usize, charge: f32) {
+        self.remove(entity);
+        self.total += 1;
+        *self.species.entry(species).or_default() += 1;
+        *self.sign_count(charge) += 1;
+        self.tracked.insert(entity, (species, charge));
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some((species, charge)) = self.tracked.remove(&entity) else {
+            return;
+        };
+        self.total -= 1;
+        *self.sign_count(charge) -= 1;
+        if let Some(count) = self.species.get_mut(&species) {
+            *count -= 1;
+            if *count == 0 {
+                self.species.remove(&species);
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct AnnihilationEvent {
+    pub position: Vec2,
+    pub energy: f32,
+}
+
+// What an annihilation releases: `photons` short lived neutral particles
+// sharing its energy, and a push on every particle within `impulse_radius`
+// that falls off linearly with distance.
 #[derive(Resource)]
-pub struct ParticleCounter(pub u16);
+pub struct AnnihilationConfig {
+    pub photons: u32,
+    pub photon_lifetime: f32,
+    pub impulse: f32,
+    pub impulse_radius: f32,
+}
 
-const DELETION_RADIUS: f32 = 10.0;
+#[derive(Resource, Default)]
+pub struct AnnihilationRate(pub WindowedRate);
+
+const PHOTON_COUNT: u32 = 2;
+const PHOTON_LIFETIME: f32 = 1.0;
+const PHOTON_MAX_SPEED: f32 = 1000.0;
+const IMPULSE_STRENGTH: f32 = 100.0;
+const IMPULSE_RADIUS: f32 = 200.0;
 
 #[allow(dead_code)]
 fn spawn_random_particles(
     mut commands: Commands,
     assets: Res<ParticleAssets>,
-    mut counter: ResMut<ParticleCounter>,
 ) { 
     for _i in 0..100 {
-        let positive = random::<bool>();
-        let x = random::<f32>() * 1000.0;
-        let y = random::<f32>() * 1000.0;
-
-        commands.spawn((
-            ParticleBundle {
-                velocity: Velocity(Vec2::ZERO),
-                charge: if positive { Charge(1.0) } else { Charge(-1.0) },
-                cancelled: Cancelled(false),
-                particle: Particle
-            },
-            MaterialMesh2dBundle {
-                mesh: assets.circle.clone(),
-                material: if positive { assets.red.clone() } else { assets.blue.clone() },
-                transform: Transform::from_xyz(x, y, 0.0), 
-                ..default()
-            }
-        ));
-
-        counter.0 += 1;
+        let charge = if random::<bool>() { 1.0 } else { -1.0 };
+        let position = Vec2::new(random::<f32>(), random::<f32>()) * 1000.0;
+        spawn_particle(&mut commands, &assets, position, Vec2::ZERO, 0, charge);
     }
 }
 
-fn cancel_collided_particles(
-    mut q: Query<(&mut Cancelled, &Charge, &Transform)>
+fn release_annihilation_energy(
+    mut commands: Commands,
+    mut events: EventReader<AnnihilationEvent>,
+    assets: Res<ParticleAssets>,
+    config: Res<AnnihilationConfig>,
+    mut q: Query<(&mut Velocity, &Transform, &Cancelled)>,
 ) {
-    let mut pairs = q.iter_combinations_mut();
-    while let Some(
-        [(mut cancelled_a, Charge(charge_a), transform_a),
-         (mut cancelled_b, Charge(charge_b), transform_b)]
-    ) = pairs.fetch_next() {
-        let distance = transform_a.translation.distance(transform_b.translation);
-        if charge_a * charge_b < 0.0 && distance < DELETION_RADIUS {
-            cancelled_a.0 = true;
-            cancelled_b.0 = true;
+    for event in events.read() {
+        if config.impulse != 0.0 {
+            for (mut velocity, transform, cancelled) in q.iter_mut() {
+                let delta = transform.translation.xy() - event.position;
+                let distance = delta.length();
+                if cancelled.0 || distance >= config.impulse_radius {
+                    continue;
+                }
+                let falloff = 1.0 - distance / config.impulse_radius;
+                velocity.0 += delta.normalize_or_zero() * config.impulse * falloff;
+            }
+        }
+
+        if config.photons == 0 {
+            continue;
+        }
+        let speed = (2.0 * event.energy / config.photons as f32).sqrt().min(PHOTON_MAX_SPEED);
+        let offset = random::<f32>() * TAU;
+        for i in 0..config.photons {
+            let angle = offset + TAU * i as f32 / config.photons as f32;
+            let velocity = Vec2::from_angle(angle) * speed;
+            spawn_particle(&mut commands, &assets, event.position, velocity, 0, 0.0).insert(Lifetime {
+                duration: config.photon_lifetime,
+                fade: AgeFade::Fade,
+                charge: None,
+                colour: None,
+            });
         }
     }
 }
 
-fn delete_cancelled_particles(
+fn update_annihilation_rate(
+    time: Res<Time>,
+    mut events: EventReader<AnnihilationEvent>,
+    mut rate: ResMut<AnnihilationRate>,
+) {
+    rate.0.add(events.read().count() as u64);
+    rate.0.tick(time.delta_seconds());
+}
+
+pub(crate) fn delete_cancelled_particles(
     mut commands: Commands,
-    mut counter: ResMut<ParticleCounter>,
     q: Query<(Entity, &Cancelled)>,
 ) {
     for (entity, cancelled) in q.iter() {
         if cancelled.0 {
             commands.entity(entity).despawn();
-            counter.0 -= 1;
         }
     }
 }
+
+pub(crate) fn register_particle_count_hooks(world: &mut World) {
+    world
+        .register_component_hooks::<Particle>()
+        .on_add(|mut world: DeferredWorld, entity, _| {
+            let species = world.get::<Species>(entity).map_or(0, |species| species.0);
+            let charge = world.get::<Charge>(entity).map_or(0.0, |charge| charge.0);
+            world.resource_mut::<ParticleCounts>().add(entity, species, charge);
+        })
+        .on_remove(|mut world: DeferredWorld, entity, _| {
+            world.resource_mut::<ParticleCounts>().remove(entity);
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn recount_changed_particles(
+    mut counts: ResMut<ParticleCounts>,
+    q: Query<(Entity, &Species, &Charge), (With<Particle>, Or<(Changed<Species>, Changed<Charge>)>)>,
+) {
+    for (entity, species, charge) in q.iter() {
+        counts.add(entity, species.0, charge.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        physics::SimRng,
+        reaction::*,
+    };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(ParticleCounts::default());
+        register_particle_count_hooks(&mut world);
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(SimRng(StdRng::seed_from_u64(0)));
+        world.insert_resource(ParticleAssets::placeholder());
+        world.insert_resource(ReactionTable(vec![
+            Reaction::Pair {
+                a: Reactant::Positive,
+                b: Reactant::Negative,
+                radius: 10.0,
+                probability: 1.0,
+                min_energy: 0.0,
+                outcome: PairOutcome::Annihilate,
+            },
+        ]));
+        world.init_resource::<Events<AnnihilationEvent>>();
+        world.init_resource::<Events<BondEvent>>();
+        world
+    }
+
+    fn spawn(world: &mut World, x: f32, charge: f32) -> Entity {
+        spawn_test_particle(world, Vec2::new(x, 0.0), Vec2::ZERO, 0, charge)
+    }
+
+    fn assert_counts_match(world: &mut World) {
+        let charges = world
+            .query_filtered::<&Charge, With<Particle>>()
+            .iter(world)
+            .map(|charge| charge.0)
+            .collect::<Vec<_>>();
+        let counts = world.resource::<ParticleCounts>();
+        assert_eq!(counts.total, charges.len());
+        assert_eq!(counts.positive, charges.iter().filter(|c| **c > 0.0).count());
+        assert_eq!(counts.negative, charges.iter().filter(|c| **c < 0.0).count());
+        assert_eq!(counts.species.get(&0).copied().unwrap_or(0), charges.len());
+    }
+
+    #[test]
+    fn counts_survive_multi_pair_annihilation() {
+        let mut world = world();
+        // One positive particle touching two negative ones, plus a
+        // bystander, all reacting in the same frame.
+        spawn(&mut world, -5.0, -1.0);
+        spawn(&mut world, 0.0, 1.0);
+        spawn(&mut world, 5.0, -1.0);
+        spawn(&mut world, 1000.0, 1.0);
+        assert_counts_match(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((apply_reactions, delete_cancelled_particles).chain());
+        schedule.run(&mut world);
+
+        assert_counts_match(&mut world);
+        assert_eq!(world.resource::<ParticleCounts>().total, 2);
+    }
+
+    #[test]
+    fn counts_follow_external_despawns_and_charge_changes() {
+        let mut world = world();
+        let a = spawn(&mut world, 0.0, 1.0);
+        let b = spawn(&mut world, 100.0, 1.0);
+
+        world.get_mut::<Charge>(a).unwrap().0 = -1.0;
+        let mut schedule = Schedule::default();
+        schedule.add_systems(recount_changed_particles);
+        schedule.run(&mut world);
+        assert_counts_match(&mut world);
+
+        world.despawn(b);
+        world.despawn(b);
+        assert_counts_match(&mut world);
+        assert_eq!(world.resource::<ParticleCounts>().total, 1);
+    }
+}
+
@@ -1,28 +1,47 @@
 use bevy::prelude::*;
+use std::f32::consts::TAU;
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
 
 use crate:: {
     particle::*,
     forcefield::ForcefieldPlugin,
     collider::ColliderPlugin,
+    external::ExternalFieldPlugin,
+    bond::BondPlugin,
+    thermostat::ThermostatPlugin,
+    emmiter::SPAWN_RATE,
+    reaction::DELETION_RADIUS,
 };
 
 pub struct PhysicsPlugin {
     pub parallel: bool,
+    pub seed: u64,
 }
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(ForcefieldPlugin { parallel: self.parallel });
         app.add_plugins(ColliderPlugin { parallel: self.parallel });
+        app.add_plugins(ExternalFieldPlugin);
+        app.add_plugins(BondPlugin);
+        app.add_plugins(ThermostatPlugin);
+        app.insert_resource(SimulationConfig::default());
+        app.insert_resource(InteractionMatrix::default());
         app.insert_resource(TotalKineticEnergy(0.0));
+        app.insert_resource(SimRng(StdRng::seed_from_u64(self.seed)));
         app.add_systems(Update, update_kinetic_energy);
         if self.parallel {
             app.add_systems(Update, apply_particle_forces_parallel);
         } else {
             app.add_systems(Update, apply_particle_forces_combination);
         }
-        app.add_systems(Update, (border_interaction, limit_speed));
-        app.add_systems(PostUpdate, apply_particle_velocities);
+        app.add_systems(Update, (border_interaction, limit_speed, apply_time_scale));
+        app.add_systems(PreUpdate, record_velocities);
+        app.add_systems(PostUpdate, (measure_accelerations, apply_particle_velocities).chain());
     }
 }
 
@@ -31,11 +50,159 @@ const BORDER_DISTANCE: f32 = 5000.0;
 const MAX_SPEED: f32 = 1000.0;
 const MAX_INTERACTION_DISTANCE: f32 = 500.0;
 const DAMPING_COEFF: f32 = 0.999;
+const MATRIX_SPECIES: usize = 6;
+const MATRIX_RADIUS: f32 = 300.0;
+const MATRIX_STRENGTH: f32 = 500.0;
+// Fraction of `MATRIX_RADIUS` below which particles repel regardless of
+// species.
+const MATRIX_REPULSION: f32 = 0.3;
+
+// Live simulation parameters. `emission_rate` is per emitter and second,
+// `deletion_radius` is the radius of the annihilation reactions and
+// `magnetic_field` points out of the screen (+z).
+#[derive(Resource, Clone, PartialEq)]
+pub struct SimulationConfig {
+    pub force_constant: f32,
+    pub interaction_radius: f32,
+    pub damping: f32,
+    pub max_speed: f32,
+    pub emission_rate: f32,
+    pub deletion_radius: f32,
+    pub boundary: BoundaryMode,
+    pub time_scale: f32,
+    pub gravity: Vec2,
+    pub electric_field: Vec2,
+    pub magnetic_field: f32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            force_constant: K,
+            interaction_radius: MAX_INTERACTION_DISTANCE,
+            damping: DAMPING_COEFF,
+            max_speed: MAX_SPEED,
+            emission_rate: SPAWN_RATE,
+            deletion_radius: DELETION_RADIUS,
+            boundary: BoundaryMode::Reflect,
+            time_scale: 1.0,
+            gravity: Vec2::ZERO,
+            electric_field: Vec2::ZERO,
+            magnetic_field: 0.0,
+        }
+    }
+}
+
+// What happens to particles leaving the arena. Only `Reflect` keeps the
+// walls.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoundaryMode {
+    Reflect,
+    Wrap,
+    Open,
+}
+
+impl BoundaryMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoundaryMode::Reflect => "reflect",
+            BoundaryMode::Wrap => "wrap",
+            BoundaryMode::Open => "open",
+        }
+    }
+
+    pub fn next(&self) -> BoundaryMode {
+        match self {
+            BoundaryMode::Reflect => BoundaryMode::Wrap,
+            BoundaryMode::Wrap => BoundaryMode::Open,
+            BoundaryMode::Open => BoundaryMode::Reflect,
+        }
+    }
+}
+
+// Species attraction for particle life. Each particle is pulled towards
+// (positive values) or pushed from (negative values) the species around it
+// within `radius` and pushed apart at close range. Species outside the
+// matrix and zero entries don't interact at all.
+#[derive(Resource, Clone, PartialEq)]
+pub struct InteractionMatrix {
+    pub size: usize,
+    pub values: Vec<f32>,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl Default for InteractionMatrix {
+    fn default() -> Self {
+        InteractionMatrix {
+            size: MATRIX_SPECIES,
+            values: vec![0.0; MATRIX_SPECIES * MATRIX_SPECIES],
+            radius: MATRIX_RADIUS,
+            strength: MATRIX_STRENGTH,
+        }
+    }
+}
+
+impl InteractionMatrix {
+    pub fn get(&self, a: usize, b: usize) -> f32 {
+        if a < self.size && b < self.size { self.values[a * self.size + b] } else { 0.0 }
+    }
+
+    pub fn set(&mut self, a: usize, b: usize, value: f32) {
+        self.values[a * self.size + b] = value.clamp(-1.0, 1.0);
+    }
+
+    pub fn randomise(&mut self, rng: &mut SimRng) {
+        for value in self.values.iter_mut() {
+            *value = rng.0.gen_range(-1.0..=1.0);
+        }
+    }
+
+    pub fn symmetrise(&mut self) {
+        for a in 0..self.size {
+            for b in a + 1..self.size {
+                let mean = 0.5 * (self.get(a, b) + self.get(b, a));
+                self.set(a, b, mean);
+                self.set(b, a, mean);
+            }
+        }
+    }
+
+    pub fn invert(&mut self) {
+        for value in self.values.iter_mut() {
+            *value = -*value;
+        }
+    }
+
+    // Acceleration of a particle towards another one `distance` away.
+    fn force(&self, attraction: f32, distance: f32) -> f32 {
+        let r = distance / self.radius;
+        let force = if r < MATRIX_REPULSION {
+            r / MATRIX_REPULSION - 1.0
+        } else if r < 1.0 {
+            attraction * (1.0 - (2.0 * r - 1.0 - MATRIX_REPULSION).abs() / (1.0 - MATRIX_REPULSION))
+        } else {
+            0.0
+        };
+        self.strength * force
+    }
+}
 
 #[derive(Resource)]
 pub struct TotalKineticEnergy(pub f32);
 
-fn update_kinetic_energy(
+// Randomness that should be reproducible between runs with the same seed.
+#[derive(Resource)]
+pub struct SimRng(pub StdRng);
+
+// Standard normal sample, by the Box-Muller transform.
+pub fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u = 1.0 - rng.gen::<f32>();
+    let v = rng.gen::<f32>();
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
+
+pub(crate) fn update_kinetic_energy(
     mut kenergy: ResMut<TotalKineticEnergy>,
     q: Query<&Velocity>,
 ) {
@@ -45,34 +212,52 @@ fn update_kinetic_energy(
     }
 }
 
-fn apply_particle_forces_combination(
+pub(crate) fn apply_particle_forces_combination(
     time: Res<Time>,
-    mut q: Query<(&mut Velocity, &Charge, &Transform)>,
+    config: Res<SimulationConfig>,
+    matrix: Res<InteractionMatrix>,
+    mut q: Query<(&mut Velocity, &Charge, &Species, &Transform)>,
 ) {
     let mut combinations = q.iter_combinations_mut();
     while let Some(
-        [(mut velocity_a, charge_a, transform_a),
-         (mut velocity_b, charge_b, transform_b)])
+        [(mut velocity_a, charge_a, species_a, transform_a),
+         (mut velocity_b, charge_b, species_b, transform_b)])
         = combinations.fetch_next()
     {
-        let force = calculate_particle_force(
+        let force_a = calculate_particle_force(
                 transform_a.translation,
                 transform_b.translation,
                 charge_a.0,
-                charge_b.0
+                charge_b.0,
+                species_a.0,
+                species_b.0,
+                &config,
+                &matrix
+        );
+        let force_b = calculate_particle_force(
+                transform_b.translation,
+                transform_a.translation,
+                charge_b.0,
+                charge_a.0,
+                species_b.0,
+                species_a.0,
+                &config,
+                &matrix
         );
-        velocity_a.0 += (force * time.delta_seconds()).xy();
-        velocity_b.0 -= (force * time.delta_seconds()).xy();
+        velocity_a.0 += (force_a * time.delta_seconds()).xy();
+        velocity_b.0 += (force_b * time.delta_seconds()).xy();
     }
 }
 
-fn apply_particle_forces_parallel(
+pub(crate) fn apply_particle_forces_parallel(
     time: Res<Time>,
-    mut q: Query<(&mut Velocity, &Charge, &Transform)>,
-    q2: Query<(&Charge, &Transform)>
+    config: Res<SimulationConfig>,
+    matrix: Res<InteractionMatrix>,
+    mut q: Query<(&mut Velocity, &Charge, &Species, &Transform)>,
+    q2: Query<(&Charge, &Species, &Transform)>
 ) {
-    q.par_iter_mut().for_each(|(mut velocity_a, charge_a, transform_a)| {
-        for (charge_b, transform_b) in q2.iter() {
+    q.par_iter_mut().for_each(|(mut velocity_a, charge_a, species_a, transform_a)| {
+        for (charge_b, species_b, transform_b) in q2.iter() {
             if transform_a == transform_b {
                 continue;
             }
@@ -80,31 +265,64 @@ fn apply_particle_forces_parallel(
                 transform_a.translation,
                 transform_b.translation,
                 charge_a.0,
-                charge_b.0
+                charge_b.0,
+                species_a.0,
+                species_b.0,
+                &config,
+                &matrix
             );
             velocity_a.0 += (force * time.delta_seconds()).xy();
         }
     });
 }
 
+// Force on particle a from particle b.
+#[allow(clippy::too_many_arguments)]
 fn calculate_particle_force(
     pos_a: Vec3,
     pos_b: Vec3,
     charge_a: f32,
-    charge_b: f32
+    charge_b: f32,
+    species_a: usize,
+    species_b: usize,
+    config: &SimulationConfig,
+    matrix: &InteractionMatrix
 ) -> Vec3 {
     let delta = pos_a - pos_b;
-    let direction = delta.normalize_or_zero();
     let distance = delta.length();
-    if distance > MAX_INTERACTION_DISTANCE { return Vec3::ZERO; }
-    let force = K * ((charge_a * charge_b) / f32::powf(distance, 2.0));
-    force * direction
+    // Coincident particles, like freshly spawned photons, have no direction
+    // to push each other in.
+    if distance <= f32::EPSILON {
+        return Vec3::ZERO;
+    }
+    let direction = delta / distance;
+    let mut force = Vec3::ZERO;
+    let attraction = matrix.get(species_a, species_b);
+    if attraction != 0.0 {
+        force -= matrix.force(attraction, distance) * direction;
+    }
+    if distance > config.interaction_radius { return force; }
+    force + config.force_constant * ((charge_a * charge_b) / f32::powf(distance, 2.0)) * direction
 }
 
 fn border_interaction(
     time: Res<Time>,
-    mut q: Query<(&mut Velocity, &Transform)>
+    config: Res<SimulationConfig>,
+    mut q: Query<(&mut Velocity, &mut Transform)>
 ) {
+    match config.boundary {
+        BoundaryMode::Reflect => {}
+        BoundaryMode::Open => return,
+        BoundaryMode::Wrap => {
+            q.par_iter_mut().for_each(|(_, mut transform)| {
+                let wrapped = (transform.translation.xy() + BORDER_DISTANCE).rem_euclid(Vec2::splat(2.0 * BORDER_DISTANCE)) - BORDER_DISTANCE;
+                if wrapped != transform.translation.xy() {
+                    transform.translation = wrapped.extend(transform.translation.z);
+                }
+            });
+            return;
+        }
+    }
     q.par_iter_mut().for_each(|(mut velocity, transform)| {
         if transform.translation.x > BORDER_DISTANCE {
             velocity.0.x = velocity.0.x.copysign(-1.0) - 0.1 * time.delta_seconds();
@@ -120,15 +338,46 @@ fn border_interaction(
 }
 
 fn limit_speed(
+    config: Res<SimulationConfig>,
     mut q: Query<&mut Velocity>
 ) {
     q.par_iter_mut().for_each(|mut velocity| {
-        velocity.0 = velocity.0.clamp_length_max(MAX_SPEED) * DAMPING_COEFF;
+        velocity.0 = velocity.0.clamp_length_max(config.max_speed) * config.damping;
     });
 }
 
+fn apply_time_scale(
+    config: Res<SimulationConfig>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if config.is_changed() && config.time_scale.is_finite() {
+        time.set_relative_speed(config.time_scale.max(0.0));
+    }
+}
+
+
+fn record_velocities(
+    mut q: Query<(&mut Acceleration, &Velocity)>,
+) {
+    q.par_iter_mut().for_each(|(mut acceleration, velocity)| {
+        acceleration.previous = velocity.0;
+    });
+}
+
+fn measure_accelerations(
+    time: Res<Time>,
+    mut q: Query<(&mut Acceleration, &Velocity)>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+    q.par_iter_mut().for_each(|(mut acceleration, velocity)| {
+        acceleration.value = (velocity.0 - acceleration.previous) / dt;
+    });
+}
 
-fn apply_particle_velocities(
+pub(crate) fn apply_particle_velocities(
     time: Res<Time>,
     mut q: Query<(&mut Transform, &Velocity)>
 ) {
@@ -136,3 +385,89 @@ fn apply_particle_velocities(
         transform.translation += velocity.0.extend(0.0) * time.delta_seconds();
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        tasks::{ComputeTaskPool, TaskPool},
+    };
+
+    use super::*;
+    use crate::particle::spawn_test_particle;
+
+    #[test]
+    fn accelerations_are_measured_from_the_spawn_velocity() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+
+        // Spawned after the velocities were recorded, like an emmiter would.
+        let entity = spawn_test_particle(&mut world, Vec2::ZERO, Vec2::new(300.0, 0.0), 0, 1.0);
+        world.run_system_once(measure_accelerations);
+        assert_eq!(world.get::<Acceleration>(entity).unwrap().value, Vec2::ZERO);
+
+        world.run_system_once(record_velocities);
+        world.get_mut::<Velocity>(entity).unwrap().0 = Vec2::new(300.0, 10.0);
+        world.run_system_once(measure_accelerations);
+        assert!(world.get::<Acceleration>(entity).unwrap().value.distance(Vec2::new(0.0, 100.0)) < 1e-3);
+    }
+
+    #[test]
+    fn matrices_are_randomised_symmetrised_and_inverted() {
+        let mut matrix = InteractionMatrix::default();
+        matrix.randomise(&mut SimRng(StdRng::seed_from_u64(1)));
+        assert!(matrix.values.iter().all(|value| (-1.0..=1.0).contains(value)));
+        assert!(matrix.values.iter().any(|value| *value != 0.0));
+        let mut again = InteractionMatrix::default();
+        again.randomise(&mut SimRng(StdRng::seed_from_u64(1)));
+        assert_eq!(matrix.values, again.values);
+
+        let random = matrix.values.clone();
+        matrix.symmetrise();
+        for a in 0..matrix.size {
+            assert_eq!(matrix.get(a, a), random[a * matrix.size + a]);
+            for b in 0..matrix.size {
+                let mean = 0.5 * (random[a * matrix.size + b] + random[b * matrix.size + a]);
+                assert!((matrix.get(a, b) - mean).abs() < 1e-6);
+                assert_eq!(matrix.get(a, b), matrix.get(b, a));
+            }
+        }
+
+        let symmetric = matrix.values.clone();
+        matrix.invert();
+        assert!(matrix.values.iter().zip(symmetric).all(|(value, before)| *value == -before));
+
+        matrix.set(0, 1, 5.0);
+        assert_eq!(matrix.get(0, 1), 1.0);
+        assert_eq!(matrix.get(0, matrix.size), 0.0);
+    }
+
+    #[test]
+    fn species_pairs_attract_and_repel_by_the_matrix() {
+        let config = SimulationConfig::default();
+        let mut matrix = InteractionMatrix::default();
+        matrix.set(0, 1, 1.0);
+        matrix.set(1, 0, -1.0);
+        let force = |distance: f32, a: usize, b: usize| {
+            calculate_particle_force(Vec3::ZERO, Vec3::new(distance, 0.0, 0.0), 0.0, 0.0, a, b, &config, &matrix).x
+        };
+
+        // Strongest halfway between the repulsive core and the radius, and
+        // not necessarily returned in kind.
+        let peak = 0.5 * (1.0 + MATRIX_REPULSION) * matrix.radius;
+        assert!((force(peak, 0, 1) - matrix.strength).abs() < 1e-3);
+        assert!((force(peak, 1, 0) + matrix.strength).abs() < 1e-3);
+        assert!(force(0.8 * peak, 0, 1) > 0.0 && force(0.8 * peak, 0, 1) < matrix.strength);
+
+        // Everything with an entry repels up close, nothing reaches past
+        // the radius.
+        assert!(force(0.1 * matrix.radius, 0, 1) < 0.0 && force(0.1 * matrix.radius, 1, 0) < 0.0);
+        assert_eq!(force(1.01 * matrix.radius, 0, 1), 0.0);
+        assert_eq!(force(peak, 0, 2), 0.0);
+        assert_eq!(force(peak, 0, MATRIX_SPECIES), 0.0);
+    }
+}
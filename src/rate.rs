@@ -0,0 +1,53 @@
+// Events per second, averaged over windows of `WINDOW` seconds so the
+// readout doesn't flicker from frame to frame.
+#[derive(Default)]
+pub struct WindowedRate {
+    pub per_second: f32,
+    count: u64,
+    elapsed: f32,
+}
+
+const WINDOW: f32 = 1.0;
+
+impl WindowedRate {
+    pub fn add(&mut self, count: u64) {
+        self.count += count;
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.elapsed += dt;
+        if self.elapsed >= WINDOW {
+            self.per_second = self.count as f32 / self.elapsed;
+            self.count = 0;
+            self.elapsed = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_averaged_over_a_window() {
+        let mut rate = WindowedRate::default();
+        for _ in 0..9 {
+            rate.add(3);
+            rate.tick(0.1);
+            assert_eq!(rate.per_second, 0.0);
+        }
+        rate.add(3);
+        rate.tick(0.1);
+        assert!((rate.per_second - 30.0).abs() < 1e-3);
+
+        // The next window starts from scratch.
+        for _ in 0..10 {
+            rate.tick(0.1);
+        }
+        assert_eq!(rate.per_second, 0.0);
+
+        rate.add(5);
+        rate.tick(2.5);
+        assert_eq!(rate.per_second, 2.0);
+    }
+}
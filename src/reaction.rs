@@ -0,0 +1,500 @@
+use bevy::{
+    prelude::*,
+    utils::HashSet,
+};
+
+use rand::Rng;
+
+use std::{
+    f32::consts::TAU,
+    fs,
+};
+
+use crate::{
+    particle::*,
+    physics::SimRng,
+    asset::ParticleAssets,
+    physics::SimulationConfig,
+};
+
+pub struct ReactionPlugin;
+
+impl Plugin for ReactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReactionTable(vec![
+            Reaction::Pair {
+                a: Reactant::Positive,
+                b: Reactant::Negative,
+                radius: DELETION_RADIUS,
+                probability: 1.0,
+                min_energy: 0.0,
+                outcome: PairOutcome::Annihilate,
+            },
+        ]));
+        app.add_event::<BondEvent>();
+        app.add_systems(Startup, load_reactions);
+        app.add_systems(Update, sync_deletion_radius.before(apply_reactions));
+    }
+}
+
+#[derive(Resource)]
+pub struct ReactionTable(pub Vec<Reaction>);
+
+pub enum Reactant {
+    Any,
+    Species(usize),
+    Positive,
+    Negative,
+    Neutral,
+}
+
+// `Pair` reactions fire with `probability` per frame for every matching pair
+// closer than `radius` whose collision energy is at least `min_energy`.
+// `Decay` reactions turn a particle into its products at `rate` per second.
+pub enum Reaction {
+    Pair {
+        a: Reactant,
+        b: Reactant,
+        radius: f32,
+        probability: f32,
+        min_energy: f32,
+        outcome: PairOutcome,
+    },
+    Decay {
+        reactant: Reactant,
+        rate: f32,
+        products: Vec<Product>,
+    },
+}
+
+pub enum PairOutcome {
+    Annihilate,
+    Transform(Vec<Product>),
+    Bond,
+}
+
+pub struct Product {
+    pub species: usize,
+    pub charge: f32,
+}
+
+#[derive(Event)]
+pub struct BondEvent {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+impl Reactant {
+    fn matches(&self, species: usize, charge: f32) -> bool {
+        match self {
+            Reactant::Any => true,
+            Reactant::Species(s) => *s == species,
+            Reactant::Positive => charge > 0.0,
+            Reactant::Negative => charge < 0.0,
+            Reactant::Neutral => charge == 0.0,
+        }
+    }
+}
+
+pub const DELETION_RADIUS: f32 = 10.0;
+const REACTIONS_PATH: &str = "presets/reactions.preset";
+const PRODUCT_SPREAD: f32 = 5.0;
+const PRODUCT_KICK: f32 = 20.0;
+
+// Replaces the built in annihilation with the reactions in
+// `REACTIONS_PATH`, if there are any.
+fn load_reactions(
+    mut table: ResMut<ReactionTable>,
+) {
+    match fs::read_to_string(REACTIONS_PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|reactions| read_reactions(&reactions))
+    {
+        Ok(reactions) => table.0 = reactions,
+        Err(error) => warn!("Could not load {REACTIONS_PATH}: {error}"),
+    }
+}
+
+// One reaction per line, either
+//   pair <reactant> <reactant> <radius> <probability> <min energy> <outcome>
+//   decay <reactant> <rate> <product>...
+// where the outcome is `annihilate`, `bond` or `transform <product>...`.
+// Reactants are `any`, `positive`, `negative`, `neutral` or a species and
+// products are written `species:charge`.
+fn read_reactions(reactions: &str) -> Result<Vec<Reaction>, String> {
+    reactions
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| read_reaction(line).map_err(|error| format!("{error} in `{line}`")))
+        .collect()
+}
+
+fn read_reaction(line: &str) -> Result<Reaction, String> {
+    match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["pair", a, b, radius, probability, min_energy, outcome, products @ ..] => {
+            let outcome = match (*outcome, products) {
+                ("annihilate", []) => PairOutcome::Annihilate,
+                ("bond", []) => PairOutcome::Bond,
+                ("transform", products) => PairOutcome::Transform(read_products(products)?),
+                _ => return Err(format!("invalid outcome `{outcome}`")),
+            };
+            Ok(Reaction::Pair {
+                a: read_reactant(a)?,
+                b: read_reactant(b)?,
+                radius: read_number(radius)?,
+                probability: read_number(probability)?.min(1.0),
+                min_energy: read_number(min_energy)?,
+                outcome,
+            })
+        }
+        ["decay", reactant, rate, products @ ..] => Ok(Reaction::Decay {
+            reactant: read_reactant(reactant)?,
+            rate: read_number(rate)?,
+            products: read_products(products)?,
+        }),
+        _ => Err("expected a pair or decay reaction".to_string()),
+    }
+}
+
+fn read_reactant(value: &str) -> Result<Reactant, String> {
+    match value {
+        "any" => Ok(Reactant::Any),
+        "positive" => Ok(Reactant::Positive),
+        "negative" => Ok(Reactant::Negative),
+        "neutral" => Ok(Reactant::Neutral),
+        species => species
+            .parse()
+            .map(Reactant::Species)
+            .map_err(|_| format!("invalid reactant `{value}`")),
+    }
+}
+
+fn read_products(values: &[&str]) -> Result<Vec<Product>, String> {
+    values
+        .iter()
+        .map(|value| {
+            value
+                .split_once(':')
+                .and_then(|(species, charge)| Some(Product {
+                    species: species.parse().ok()?,
+                    charge: charge.parse().ok().filter(|charge: &f32| charge.is_finite())?,
+                }))
+                .ok_or_else(|| format!("invalid product `{value}`"))
+        })
+        .collect()
+}
+
+// Radii, probabilities, energies and rates are all finite and positive.
+fn read_number(value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .ok_or_else(|| format!("invalid number `{value}`"))
+}
+
+fn sync_deletion_radius(
+    config: Res<SimulationConfig>,
+    mut table: ResMut<ReactionTable>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    for reaction in table.0.iter_mut() {
+        if let Reaction::Pair { radius, outcome: PairOutcome::Annihilate, .. } = reaction {
+            *radius = config.deletion_radius;
+        }
+    }
+}
+
+struct Reactor {
+    entity: Entity,
+    species: usize,
+    charge: f32,
+    position: Vec2,
+    velocity: Vec2,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_reactions(
+    mut commands: Commands,
+    time: Res<Time>,
+    table: Res<ReactionTable>,
+    assets: Res<ParticleAssets>,
+    mut rng: ResMut<SimRng>,
+    mut annihilations: EventWriter<AnnihilationEvent>,
+    mut bonds: EventWriter<BondEvent>,
+    mut q: Query<(Entity, &Species, &Charge, &Transform, &Velocity, &mut Cancelled)>,
+) {
+    let reactors = q
+        .iter()
+        .filter(|(.., cancelled)| !cancelled.0)
+        .map(|(entity, species, charge, transform, velocity, _)| Reactor {
+            entity,
+            species: species.0,
+            charge: charge.0,
+            position: transform.translation.xy(),
+            velocity: velocity.0,
+        })
+        .collect::<Vec<_>>();
+
+    let mut consumed = HashSet::new();
+    let mut products = Vec::new();
+
+    let max_radius = table.0.iter().fold(0.0_f32, |max, reaction| match reaction {
+        Reaction::Pair { radius, .. } => max.max(*radius),
+        Reaction::Decay { .. } => max,
+    });
+    for (i, a) in reactors.iter().enumerate() {
+        for b in reactors[i + 1..].iter() {
+            if consumed.contains(&a.entity) {
+                break;
+            }
+            if consumed.contains(&b.entity) || a.position.distance(b.position) >= max_radius {
+                continue;
+            }
+            for reaction in table.0.iter() {
+                let Reaction::Pair { a: ra, b: rb, radius, probability, min_energy, outcome } = reaction else {
+                    continue;
+                };
+                let ordered = ra.matches(a.species, a.charge) && rb.matches(b.species, b.charge);
+                let swapped = ra.matches(b.species, b.charge) && rb.matches(a.species, a.charge);
+                // Collision energy in the centre of mass frame for unit masses.
+                let energy = 0.25 * a.velocity.distance_squared(b.velocity);
+                if !(ordered || swapped)
+                    || a.position.distance(b.position) >= *radius
+                    || energy < *min_energy
+                    || rng.0.gen::<f32>() >= *probability
+                {
+                    continue;
+                }
+
+                let position = a.position.lerp(b.position, 0.5);
+                match outcome {
+                    PairOutcome::Annihilate => {
+                        annihilations.send(AnnihilationEvent {
+                            position,
+                            energy: 0.5 * (a.velocity.length_squared() + b.velocity.length_squared()),
+                        });
+                    }
+                    PairOutcome::Transform(list) => {
+                        let velocity = a.velocity.lerp(b.velocity, 0.5);
+                        products.extend(spread_products(list, position, velocity, &mut rng));
+                    }
+                    PairOutcome::Bond => {
+                        bonds.send(BondEvent { a: a.entity, b: b.entity });
+                        break;
+                    }
+                }
+                consumed.insert(a.entity);
+                consumed.insert(b.entity);
+                break;
+            }
+        }
+    }
+
+    for reactor in reactors.iter() {
+        if consumed.contains(&reactor.entity) {
+            continue;
+        }
+        for reaction in table.0.iter() {
+            let Reaction::Decay { reactant, rate, products: list } = reaction else {
+                continue;
+            };
+            let probability = 1.0 - (-rate * time.delta_seconds()).exp();
+            if reactant.matches(reactor.species, reactor.charge) && rng.0.gen::<f32>() < probability {
+                products.extend(spread_products(list, reactor.position, reactor.velocity, &mut rng));
+                consumed.insert(reactor.entity);
+                break;
+            }
+        }
+    }
+
+    for entity in consumed {
+        if let Ok((.., mut cancelled)) = q.get_mut(entity) {
+            cancelled.0 = true;
+        }
+    }
+
+    for (product, position, velocity) in products {
+        spawn_particle(&mut commands, &assets, position, velocity, product.species, product.charge);
+    }
+}
+
+// Several products start evenly spaced on a small circle around the
+// reaction and fly apart, so they never sit on top of each other. The kicks
+// cancel out, leaving the products with the reactants' velocity on average.
+fn spread_products<'a>(
+    list: &'a [Product],
+    position: Vec2,
+    velocity: Vec2,
+    rng: &mut SimRng,
+) -> Vec<(&'a Product, Vec2, Vec2)> {
+    if list.len() < 2 {
+        return list.iter().map(|product| (product, position, velocity)).collect();
+    }
+    let offset = rng.0.gen::<f32>() * TAU;
+    list.iter()
+        .enumerate()
+        .map(|(i, product)| {
+            let direction = Vec2::from_angle(offset + TAU * i as f32 / list.len() as f32);
+            (product, position + direction * PRODUCT_SPREAD, velocity + direction * PRODUCT_KICK)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::tasks::{ComputeTaskPool, TaskPool};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::physics::{InteractionMatrix, apply_particle_forces_combination, apply_particle_forces_parallel};
+
+    fn world(reactions: &str) -> World {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_millis(100));
+        world.insert_resource(time);
+        world.insert_resource(SimRng(StdRng::seed_from_u64(0)));
+        world.insert_resource(ParticleAssets::placeholder());
+        world.insert_resource(ReactionTable(read_reactions(reactions).unwrap()));
+        world.init_resource::<Events<AnnihilationEvent>>();
+        world.init_resource::<Events<BondEvent>>();
+        world
+    }
+
+    // Marks the particles a test starts with, to tell them from products.
+    #[derive(Component)]
+    struct Initial;
+
+    fn spawn(world: &mut World, position: Vec2, velocity: Vec2, species: usize, charge: f32) -> Entity {
+        let entity = spawn_test_particle(world, position, velocity, species, charge);
+        world.entity_mut(entity).insert(Initial);
+        entity
+    }
+
+    fn react(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_reactions);
+        schedule.run(world);
+    }
+
+    fn cancelled(world: &World, entity: Entity) -> bool {
+        world.get::<Cancelled>(entity).unwrap().0
+    }
+
+    // Species and charge of every particle spawned by a reaction.
+    fn products(world: &mut World) -> Vec<(usize, f32)> {
+        world
+            .query_filtered::<(&Species, &Charge), Without<Initial>>()
+            .iter(world)
+            .map(|(species, charge)| (species.0, charge.0))
+            .collect()
+    }
+
+    #[test]
+    fn reaction_files_parse() {
+        let reactions = read_reactions(include_str!("../presets/reactions.preset")).unwrap();
+        assert!(matches!(reactions[0], Reaction::Pair { outcome: PairOutcome::Annihilate, .. }));
+
+        assert!(read_reactions("pair any 0 10 1 0 bond").is_ok());
+        assert!(read_reactions("pair any 0 10 1 0 explode").is_err());
+        assert!(read_reactions("pair any 0 NaN 1 0 annihilate").is_err());
+        assert!(read_reactions("decay 1 -1 0:0").is_err());
+        assert!(read_reactions("decay 1 1 0").is_err());
+    }
+
+    #[test]
+    fn decay_replaces_the_particle_with_its_products() {
+        let mut world = world("decay 1 1000 2:0 3:-1");
+        let decaying = spawn(&mut world, Vec2::ZERO, Vec2::ZERO, 1, 1.0);
+        let stable = spawn(&mut world, Vec2::new(100.0, 0.0), Vec2::ZERO, 0, 1.0);
+        react(&mut world);
+
+        assert!(cancelled(&world, decaying));
+        assert!(!cancelled(&world, stable));
+        assert_eq!(products(&mut world), vec![(2, 0.0), (3, -1.0)]);
+    }
+
+    #[test]
+    fn decay_products_spread_apart() {
+        let mut world = world("decay 1 1000 0:0 0:0 0:1 0:-1");
+        let decaying = spawn(&mut world, Vec2::ZERO, Vec2::new(10.0, 0.0), 1, 1.0);
+        react(&mut world);
+        world.despawn(decaying);
+
+        let state = world
+            .query_filtered::<(&Transform, &Velocity), Without<Initial>>()
+            .iter(&world)
+            .map(|(transform, velocity)| (transform.translation.xy(), velocity.0))
+            .collect::<Vec<_>>();
+        assert_eq!(state.len(), 4);
+        for (i, (a, _)) in state.iter().enumerate() {
+            assert!(state[i + 1..].iter().all(|(b, _)| a.distance(*b) > 1.0));
+        }
+        let mean = state.iter().map(|(_, velocity)| *velocity).sum::<Vec2>() / 4.0;
+        assert!(mean.distance(Vec2::new(10.0, 0.0)) < 1e-3, "mean velocity {mean}");
+
+        // Stacked products used to divide by a zero distance here.
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        world.insert_resource(SimulationConfig::default());
+        world.insert_resource(InteractionMatrix::default());
+        let mut schedule = Schedule::default();
+        schedule.add_systems((apply_particle_forces_combination, apply_particle_forces_parallel));
+        schedule.run(&mut world);
+        assert!(world.query::<&Velocity>().iter(&world).all(|velocity| velocity.0.is_finite()));
+    }
+
+    #[test]
+    fn transform_replaces_both_particles() {
+        let mut world = world("pair 1 2 20 1 0 transform 3:0");
+        let a = spawn(&mut world, Vec2::ZERO, Vec2::ZERO, 1, 0.0);
+        let b = spawn(&mut world, Vec2::new(10.0, 0.0), Vec2::ZERO, 2, 0.0);
+        let far = spawn(&mut world, Vec2::new(100.0, 0.0), Vec2::ZERO, 2, 0.0);
+        react(&mut world);
+
+        assert!(cancelled(&world, a) && cancelled(&world, b));
+        assert!(!cancelled(&world, far));
+        assert_eq!(products(&mut world), vec![(3, 0.0)]);
+        let position = world
+            .query_filtered::<&Transform, Without<Initial>>()
+            .single(&world)
+            .translation;
+        assert_eq!(position, Vec3::new(5.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn pairs_react_with_their_probability() {
+        let mut world = world("pair 1 2 20 0.5 0 annihilate");
+        let pairs = (0..400)
+            .map(|i| {
+                let position = Vec2::new(i as f32 * 100.0, 0.0);
+                spawn(&mut world, position, Vec2::ZERO, 1, 0.0);
+                spawn(&mut world, position + Vec2::X, Vec2::ZERO, 2, 0.0)
+            })
+            .collect::<Vec<_>>();
+        react(&mut world);
+
+        let reacted = pairs.iter().filter(|entity| cancelled(&world, **entity)).count();
+        assert!((150..250).contains(&reacted), "{reacted} of 400 pairs reacted");
+    }
+
+    #[test]
+    fn pairs_need_the_minimum_collision_energy() {
+        // 0.25 * 200^2 = 10000 in the centre of mass frame.
+        let mut world = world("pair 1 2 20 1 10000 annihilate");
+        let slow = spawn(&mut world, Vec2::ZERO, Vec2::new(99.0, 0.0), 1, 0.0);
+        spawn(&mut world, Vec2::X, Vec2::new(-99.0, 0.0), 2, 0.0);
+        let fast = spawn(&mut world, Vec2::new(100.0, 0.0), Vec2::new(101.0, 0.0), 1, 0.0);
+        spawn(&mut world, Vec2::new(101.0, 0.0), Vec2::new(-101.0, 0.0), 2, 0.0);
+        react(&mut world);
+
+        assert!(!cancelled(&world, slow));
+        assert!(cancelled(&world, fast));
+        assert_eq!(world.resource::<Events<AnnihilationEvent>>().len(), 1);
+    }
+}
@@ -0,0 +1,315 @@
+use bevy::{
+    prelude::*,
+    core_pipeline::core_2d::Transparent2d,
+    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    math::FloatOrd,
+    render::{
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+            RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        texture::BevyDefault,
+        view::{ExtractedView, ViewTarget, VisibilitySystems},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    sprite::{Mesh2dHandle, Mesh2dPipeline, Mesh2dPipelineKey, SetMesh2dViewBindGroup},
+    utils::HashMap,
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    particle::*,
+    asset::ParticleAssets,
+};
+
+pub struct ParticleRenderPlugin;
+
+impl Plugin for ParticleRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ParticleRenderMode::Instanced);
+        app.add_systems(PostUpdate, sync_particle_meshes.before(VisibilitySystems::CalculateBounds));
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(ParticleInstanceBuffer(RawBufferVec::new(BufferUsages::VERTEX)))
+            .init_resource::<SpecializedRenderPipelines<ParticlePipeline>>()
+            .add_render_command::<Transparent2d, DrawParticles>()
+            .add_systems(ExtractSchedule, extract_particle_instances)
+            .add_systems(Render, (
+                    queue_particles.in_set(RenderSet::Queue),
+                    prepare_particle_instances.in_set(RenderSet::PrepareResources))
+                );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp).init_resource::<ParticlePipeline>();
+    }
+}
+
+// `Meshes` gives every particle its own mesh and material, `Instanced`
+// spawns none and draws all particles in a single instanced draw call,
+// taking colours straight from their `ParticleColour`.
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleRenderMode {
+    Meshes,
+    Instanced,
+}
+
+const PARTICLE_RADIUS: f32 = 10.0;
+const SHADER_PATH: &str = "shaders/particles.wgsl";
+
+// Two `Float32x4` attributes, so there must be no padding for the `Pod`
+// impl to hold.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct ParticleInstance {
+    position: Vec3,
+    radius: f32,
+    colour: [f32; 4],
+}
+
+const _: () = assert!(std::mem::size_of::<ParticleInstance>() == 32);
+
+unsafe impl Zeroable for ParticleInstance {}
+unsafe impl Pod for ParticleInstance {}
+
+// Render world entity the instanced draw is queued for.
+#[derive(Component)]
+struct ParticleBatch;
+
+#[allow(clippy::type_complexity)]
+fn sync_particle_meshes(
+    mut commands: Commands,
+    mode: Res<ParticleRenderMode>,
+    assets: Res<ParticleAssets>,
+    mut q: Query<(Entity, Ref<ParticleColour>, Option<&mut Handle<ColorMaterial>>), With<Particle>>,
+) {
+    for (entity, colour, material) in q.iter_mut() {
+        match (*mode, material) {
+            (ParticleRenderMode::Meshes, None) => {
+                commands.entity(entity).try_insert((assets.circle.clone(), colour.0.clone()));
+            }
+            (ParticleRenderMode::Meshes, Some(mut material)) => {
+                if colour.is_changed() && *material != colour.0 {
+                    *material = colour.0.clone();
+                }
+            }
+            (ParticleRenderMode::Instanced, Some(_)) => {
+                commands.entity(entity).remove::<(Mesh2dHandle, Handle<ColorMaterial>)>();
+            }
+            (ParticleRenderMode::Instanced, None) => {}
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ParticleInstanceBuffer(RawBufferVec<ParticleInstance>);
+
+// Fills the instance buffer straight from the main world, it is uploaded
+// as is in `prepare_particle_instances`.
+fn extract_particle_instances(
+    mut commands: Commands,
+    mode: Extract<Res<ParticleRenderMode>>,
+    materials: Extract<Res<Assets<ColorMaterial>>>,
+    q: Extract<Query<(&GlobalTransform, &ParticleColour), With<Particle>>>,
+    mut buffer: ResMut<ParticleInstanceBuffer>,
+) {
+    buffer.0.clear();
+    if **mode == ParticleRenderMode::Meshes {
+        return;
+    }
+
+    fill_instances(&mut buffer.0, &materials, q.iter());
+    if !buffer.0.is_empty() {
+        commands.spawn(ParticleBatch);
+    }
+}
+
+fn fill_instances<'a>(
+    buffer: &mut RawBufferVec<ParticleInstance>,
+    materials: &Assets<ColorMaterial>,
+    particles: impl Iterator<Item = (&'a GlobalTransform, &'a ParticleColour)>,
+) {
+    let mut colours = HashMap::new();
+    for (transform, ParticleColour(material)) in particles {
+        let colour = *colours.entry(material.id()).or_insert_with(|| {
+            let colour = materials.get(material).map_or(Color::WHITE, |material| material.color);
+            LinearRgba::from(colour).to_f32_array()
+        });
+        let (scale, _, position) = transform.to_scale_rotation_translation();
+        buffer.push(ParticleInstance {
+            position,
+            radius: PARTICLE_RADIUS * scale.x,
+            colour,
+        });
+    }
+}
+
+fn prepare_particle_instances(
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    mut buffer: ResMut<ParticleInstanceBuffer>,
+) {
+    buffer.0.write_buffer(&device, &queue);
+}
+
+#[derive(Resource)]
+struct ParticlePipeline {
+    shader: Handle<Shader>,
+    view_layout: BindGroupLayout,
+}
+
+impl FromWorld for ParticlePipeline {
+    fn from_world(world: &mut World) -> Self {
+        ParticlePipeline {
+            shader: world.load_asset(SHADER_PATH),
+            view_layout: world.resource::<Mesh2dPipeline>().view_layout.clone(),
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for ParticlePipeline {
+    type Key = Mesh2dPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let format = if key.contains(Mesh2dPipelineKey::HDR) {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+
+        RenderPipelineDescriptor {
+            label: Some("particle_instance_pipeline".into()),
+            layout: vec![self.view_layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: VertexState {
+                shader: self.shader.clone(),
+                shader_defs: vec![],
+                entry_point: "vertex".into(),
+                buffers: vec![VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ParticleInstance>() as u64,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: vec![
+                        VertexAttribute {
+                            format: VertexFormat::Float32x4,
+                            offset: 0,
+                            shader_location: 0,
+                        },
+                        VertexAttribute {
+                            format: VertexFormat::Float32x4,
+                            offset: VertexFormat::Float32x4.size(),
+                            shader_location: 1,
+                        },
+                    ],
+                }],
+            },
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: key.msaa_samples(),
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        }
+    }
+}
+
+type DrawParticles = (
+    SetItemPipeline,
+    SetMesh2dViewBindGroup<0>,
+    DrawParticleInstances,
+);
+
+struct DrawParticleInstances;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawParticleInstances {
+    type Param = SRes<ParticleInstanceBuffer>;
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: Option<()>,
+        buffer: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let buffer = &buffer.into_inner().0;
+        let Some(instances) = buffer.buffer() else {
+            return RenderCommandResult::Failure;
+        };
+        // Each instance is a quad built from the vertex index in the shader.
+        pass.set_vertex_buffer(0, instances.slice(..));
+        pass.draw(0..6, 0..buffer.len() as u32);
+        RenderCommandResult::Success
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_particles(
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    pipeline: Res<ParticlePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<ParticlePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    q_views: Query<(Entity, &ExtractedView)>,
+    q_batches: Query<Entity, With<ParticleBatch>>,
+) {
+    let draw_function = draw_functions.read().id::<DrawParticles>();
+    for (view_entity, view) in q_views.iter() {
+        let Some(phase) = phases.get_mut(&view_entity) else {
+            continue;
+        };
+        let key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples()) | Mesh2dPipelineKey::from_hdr(view.hdr);
+        let pipeline = pipelines.specialize(&pipeline_cache, &pipeline, key);
+        for entity in q_batches.iter() {
+            phase.add(Transparent2d {
+                sort_key: FloatOrd(0.0),
+                entity,
+                pipeline,
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_carry_position_radius_and_colour() {
+        let mut materials = Assets::<ColorMaterial>::default();
+        let red = materials.add(ColorMaterial::from_color(LinearRgba::RED));
+        let missing = Handle::weak_from_u128(1);
+        let particles = [
+            (GlobalTransform::from_xyz(10.0, -20.0, 1.0), ParticleColour(red)),
+            (GlobalTransform::from(Transform::from_xyz(5.0, 5.0, 0.0).with_scale(Vec3::splat(2.0))), ParticleColour(missing)),
+        ];
+
+        let mut buffer = RawBufferVec::new(BufferUsages::VERTEX);
+        fill_instances(&mut buffer, &materials, particles.iter().map(|(transform, colour)| (transform, colour)));
+
+        // Laid out the way the vertex attributes read it.
+        let floats: &[f32] = bytemuck::cast_slice(buffer.values());
+        assert_eq!(floats, [
+            10.0, -20.0, 1.0, PARTICLE_RADIUS, 1.0, 0.0, 0.0, 1.0,
+            5.0, 5.0, 0.0, 2.0 * PARTICLE_RADIUS, 1.0, 1.0, 1.0, 1.0,
+        ]);
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::{
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+    utils::HashMap,
+};
+
+use rand::Rng;
+
+use crate::{
+    particle::*,
+    asset::SinkAssets,
+    emmiter::Emmiter,
+    physics::SimRng,
+    rate::WindowedRate,
+};
+
+pub struct SinkPlugin;
+
+impl Plugin for SinkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SinkCounters::default());
+        app.add_systems(Startup, spawn_sinks);
+        app.add_systems(Update, (absorb_particles, update_sink_rates).chain());
+    }
+}
+
+#[derive(Component)]
+pub struct Sink {
+    pub region: SinkRegion,
+    pub mode: SinkMode,
+}
+
+pub enum SinkRegion {
+    Circle { center: Vec2, radius: f32 },
+    Rect(Rect),
+}
+
+// Absorbed particles are either removed or sent back out of a random emitter.
+pub enum SinkMode {
+    Despawn,
+    Teleport,
+}
+
+#[derive(Default)]
+pub struct SinkCount {
+    pub total: u64,
+    pub rate: WindowedRate,
+}
+
+#[derive(Resource, Default)]
+pub struct SinkCounters(pub HashMap<Entity, SinkCount>);
+
+impl SinkRegion {
+    fn contains(&self, position: Vec2) -> bool {
+        match self {
+            SinkRegion::Circle { center, radius } => position.distance_squared(*center) < radius * radius,
+            SinkRegion::Rect(rect) => rect.contains(position),
+        }
+    }
+}
+
+const SINK_RADIUS: f32 = 300.0;
+
+fn spawn_sinks(
+    mut commands: Commands,
+    assets: Res<SinkAssets>,
+) {
+    let center = Vec2::new(3000.0, 0.0);
+    commands.spawn((
+        Sink {
+            region: SinkRegion::Circle { center, radius: SINK_RADIUS },
+            mode: SinkMode::Despawn,
+        },
+        MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material: assets.black.clone(),
+            transform: Transform::from_translation(center.extend(-1.0))
+                .with_scale(Vec3::splat(SINK_RADIUS)),
+            ..default()
+        }
+    ));
+
+    let rect = Rect::new(-3500.0, 2500.0, -2500.0, 3000.0);
+    commands.spawn((
+        Sink {
+            region: SinkRegion::Rect(rect),
+            mode: SinkMode::Teleport,
+        },
+        MaterialMesh2dBundle {
+            mesh: assets.square.clone(),
+            material: assets.black.clone(),
+            transform: Transform::from_translation(rect.center().extend(-1.0))
+                .with_scale(rect.size().extend(1.0)),
+            ..default()
+        }
+    ));
+}
+
+fn absorb_particles(
+    mut counters: ResMut<SinkCounters>,
+    mut rng: ResMut<SimRng>,
+    q_sinks: Query<(Entity, &Sink)>,
+    q_emmiters: Query<&Emmiter>,
+    mut q_particles: Query<(&mut Cancelled, &mut Transform, &mut Velocity), With<Particle>>,
+) {
+    let emmiters = q_emmiters.iter().collect::<Vec<_>>();
+    for (entity, sink) in q_sinks.iter() {
+        let count = counters.0.entry(entity).or_default();
+        for (mut cancelled, mut transform, mut velocity) in q_particles.iter_mut() {
+            if cancelled.0 || !sink.region.contains(transform.translation.xy()) {
+                continue;
+            }
+            count.total += 1;
+            count.rate.add(1);
+            match sink.mode {
+                SinkMode::Teleport if !emmiters.is_empty() => {
+                    let emmiter = emmiters[rng.0.gen_range(0..emmiters.len())];
+                    transform.translation = emmiter.transform.translation;
+                    velocity.0 = emmiter.velocity;
+                }
+                _ => cancelled.0 = true,
+            }
+        }
+    }
+    counters.0.retain(|entity, _| q_sinks.contains(*entity));
+}
+
+fn update_sink_rates(
+    time: Res<Time>,
+    mut counters: ResMut<SinkCounters>,
+) {
+    for count in counters.0.values_mut() {
+        count.rate.tick(time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.insert_resource(SinkCounters::default());
+        world.insert_resource(SimRng(StdRng::seed_from_u64(0)));
+        world
+    }
+
+    fn spawn(world: &mut World, position: Vec2) -> Entity {
+        world.spawn((
+            Particle,
+            Cancelled(false),
+            Transform::from_translation(position.extend(0.0)),
+            Velocity(Vec2::X),
+        )).id()
+    }
+
+    fn absorb(world: &mut World) {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(absorb_particles);
+        schedule.run(world);
+    }
+
+    #[test]
+    fn sinks_count_the_particles_they_absorb() {
+        let mut world = world();
+        let sink = world.spawn(Sink {
+            region: SinkRegion::Circle { center: Vec2::ZERO, radius: 10.0 },
+            mode: SinkMode::Despawn,
+        }).id();
+        let inside = [spawn(&mut world, Vec2::ZERO), spawn(&mut world, Vec2::new(0.0, 9.0))];
+        let outside = spawn(&mut world, Vec2::new(11.0, 0.0));
+        absorb(&mut world);
+
+        assert!(inside.iter().all(|particle| world.get::<Cancelled>(*particle).unwrap().0));
+        assert!(!world.get::<Cancelled>(outside).unwrap().0);
+        // Particles already on their way out aren't counted twice.
+        absorb(&mut world);
+        assert_eq!(world.resource::<SinkCounters>().0[&sink].total, 2);
+    }
+
+    #[test]
+    fn teleporting_sinks_send_particles_back_to_an_emmiter() {
+        let mut world = world();
+        let sink = world.spawn(Sink {
+            region: SinkRegion::Rect(Rect::new(0.0, 0.0, 10.0, 10.0)),
+            mode: SinkMode::Teleport,
+        }).id();
+        let mut emmiter = Emmiter::new(Vec2::new(500.0, 0.0));
+        emmiter.velocity = Vec2::Y;
+        world.spawn(emmiter);
+        let particle = spawn(&mut world, Vec2::new(5.0, 5.0));
+        absorb(&mut world);
+
+        assert!(!world.get::<Cancelled>(particle).unwrap().0);
+        assert_eq!(world.get::<Transform>(particle).unwrap().translation, Vec3::new(500.0, 0.0, 0.0));
+        assert_eq!(world.get::<Velocity>(particle).unwrap().0, Vec2::Y);
+        assert_eq!(world.resource::<SinkCounters>().0[&sink].total, 1);
+    }
+
+    #[test]
+    fn counters_go_away_with_their_sink() {
+        let mut world = world();
+        let sink = world.spawn(Sink {
+            region: SinkRegion::Circle { center: Vec2::ZERO, radius: 10.0 },
+            mode: SinkMode::Despawn,
+        }).id();
+        absorb(&mut world);
+        assert!(world.resource::<SinkCounters>().0.contains_key(&sink));
+
+        world.despawn(sink);
+        absorb(&mut world);
+        assert!(world.resource::<SinkCounters>().0.is_empty());
+    }
+}
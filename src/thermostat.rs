@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+
+use crate::{
+    particle::*,
+    physics::{SimRng, TotalKineticEnergy, gaussian, update_kinetic_energy},
+};
+
+pub struct ThermostatPlugin;
+
+impl Plugin for ThermostatPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Thermostat::Off);
+        app.add_systems(Update, apply_thermostat.after(update_kinetic_energy));
+    }
+}
+
+// Temperatures are in the units of `temperature`, i.e. the mean kinetic
+// energy per particle for unit masses in two dimensions.
+#[derive(Resource, Clone, Copy)]
+pub enum Thermostat {
+    Off,
+    Berendsen { target: f32, tau: f32 },
+    VelocityRescale { target: f32 },
+    Langevin { target: f32, friction: f32 },
+}
+
+impl Thermostat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Thermostat::Off => "Off",
+            Thermostat::Berendsen { .. } => "Berendsen",
+            Thermostat::VelocityRescale { .. } => "Velocity rescaling",
+            Thermostat::Langevin { .. } => "Langevin",
+        }
+    }
+
+    pub fn target(&self) -> Option<f32> {
+        match self {
+            Thermostat::Off => None,
+            Thermostat::Berendsen { target, .. }
+            | Thermostat::VelocityRescale { target }
+            | Thermostat::Langevin { target, .. } => Some(*target),
+        }
+    }
+
+    pub fn next(&self) -> Thermostat {
+        let target = self.target().unwrap_or(TARGET_TEMPERATURE);
+        match self {
+            Thermostat::Off => Thermostat::Berendsen { target, tau: BERENDSEN_TAU },
+            Thermostat::Berendsen { .. } => Thermostat::VelocityRescale { target },
+            Thermostat::VelocityRescale { .. } => Thermostat::Langevin { target, friction: LANGEVIN_FRICTION },
+            Thermostat::Langevin { .. } => Thermostat::Off,
+        }
+    }
+}
+
+const TARGET_TEMPERATURE: f32 = 20000.0;
+const BERENDSEN_TAU: f32 = 1.0;
+const LANGEVIN_FRICTION: f32 = 0.5;
+
+pub fn temperature(kenergy: &TotalKineticEnergy, count: usize) -> f32 {
+    if count == 0 {
+        return 0.0;
+    }
+    kenergy.0 / (2.0 * count as f32)
+}
+
+fn apply_thermostat(
+    time: Res<Time>,
+    thermostat: Res<Thermostat>,
+    kenergy: Res<TotalKineticEnergy>,
+    counts: Res<ParticleCounts>,
+    mut rng: ResMut<SimRng>,
+    mut q: Query<&mut Velocity>,
+) {
+    let dt = time.delta_seconds();
+    let current = temperature(&kenergy, counts.total);
+    let scale = match *thermostat {
+        Thermostat::Off => return,
+        Thermostat::Langevin { target, friction } => {
+            let noise = (2.0 * friction * target * dt).sqrt();
+            for mut velocity in q.iter_mut() {
+                let kick = Vec2::new(gaussian(&mut rng.0), gaussian(&mut rng.0));
+                let drag = -friction * velocity.0 * dt;
+                velocity.0 += drag + noise * kick;
+            }
+            return;
+        }
+        _ if current <= 0.0 => return,
+        Thermostat::Berendsen { target, tau } => {
+            (1.0 + dt / tau * (target / current - 1.0)).max(0.0).sqrt()
+        }
+        Thermostat::VelocityRescale { target } => (target / current).sqrt(),
+    };
+    q.par_iter_mut().for_each(|mut velocity| {
+        velocity.0 *= scale;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    const DT: f32 = 0.01;
+    const COUNT: usize = 100;
+
+    fn world(thermostat: Thermostat, speed: f32, seed: u64) -> World {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(DT));
+        world.insert_resource(time);
+        world.insert_resource(thermostat);
+        world.insert_resource(TotalKineticEnergy(0.0));
+        let mut counts = ParticleCounts::default();
+        counts.total = COUNT;
+        world.insert_resource(counts);
+        world.insert_resource(SimRng(StdRng::seed_from_u64(seed)));
+        for i in 0..COUNT {
+            world.spawn(Velocity(Vec2::from_angle(i as f32) * speed));
+        }
+        world
+    }
+
+    fn step(world: &mut World, steps: usize) -> f32 {
+        let mut schedule = Schedule::default();
+        schedule.add_systems((update_kinetic_energy, apply_thermostat).chain());
+        for _ in 0..steps {
+            schedule.run(world);
+        }
+        current_temperature(world)
+    }
+
+    fn current_temperature(world: &mut World) -> f32 {
+        let kenergy = world.query::<&Velocity>().iter(world).map(|velocity| velocity.0.length_squared()).sum();
+        temperature(&TotalKineticEnergy(kenergy), COUNT)
+    }
+
+    fn velocities(world: &mut World) -> Vec<Vec2> {
+        world.query::<&Velocity>().iter(world).map(|velocity| velocity.0).collect()
+    }
+
+    #[test]
+    fn off_leaves_velocities_alone() {
+        let mut world = world(Thermostat::Off, 50.0, 0);
+        let before = velocities(&mut world);
+        step(&mut world, 10);
+        assert_eq!(velocities(&mut world), before);
+    }
+
+    #[test]
+    fn velocity_rescaling_hits_the_target() {
+        let mut world = world(Thermostat::VelocityRescale { target: 200.0 }, 50.0, 0);
+        assert_eq!(current_temperature(&mut world), 1250.0);
+        let temperature = step(&mut world, 1);
+        assert!((temperature - 200.0).abs() < 0.1, "temperature {temperature}");
+    }
+
+    #[test]
+    fn berendsen_relaxes_towards_the_target() {
+        let mut world = world(Thermostat::Berendsen { target: 200.0, tau: 0.1 }, 50.0, 0);
+        let mut previous = current_temperature(&mut world);
+        for _ in 0..10 {
+            let temperature = step(&mut world, 1);
+            assert!(temperature < previous && temperature > 200.0);
+            previous = temperature;
+        }
+        let temperature = step(&mut world, 200);
+        assert!((temperature - 200.0).abs() < 1.0, "temperature {temperature}");
+    }
+
+    #[test]
+    fn langevin_heats_a_cold_system_reproducibly() {
+        let thermostat = Thermostat::Langevin { target: 200.0, friction: 5.0 };
+        let mut a = world(thermostat, 0.0, 7);
+        let mut b = world(thermostat, 0.0, 7);
+        step(&mut a, 500);
+        step(&mut b, 500);
+        assert_eq!(velocities(&mut a), velocities(&mut b));
+
+        // Averaged over a while to smooth out the noise.
+        let mean = (0..100).map(|_| step(&mut a, 5)).sum::<f32>() / 100.0;
+        assert!((mean - 200.0).abs() < 30.0, "mean temperature {mean}");
+    }
+}
@@ -0,0 +1,665 @@
+use bevy::{
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+    math::bounding::Aabb2d,
+};
+
+use bevy_pancam::PanCam;
+
+use rand::random;
+
+use std::f32::consts::TAU;
+
+use crate::{
+    particle::*,
+    asset::{ParticleAssets, ForcefieldAssets, ColliderAssets},
+    emmiter::{Emmiter, EmmiterAttachment, EmittedBy, MAX_PARTICLE_COUNT},
+    forcefield::{Forcefield, ForcefieldKind, Modulation},
+    collider::Collider,
+    physics::InteractionMatrix,
+    reaction::BondEvent,
+    ui::CursorPosition,
+};
+
+pub struct ToolsPlugin;
+
+impl Plugin for ToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tool::Select);
+        app.insert_resource(ToolSettings {
+            species: 0,
+            charge: 1.0,
+            brush_radius: BRUSH_RADIUS,
+            force: ForceMode::Attract,
+            drag_start: None,
+            bond_start: None,
+        });
+        app.add_systems(Startup, spawn_tool_text);
+        app.add_systems(Update, (
+                select_tool,
+                paint_particles.run_if(resource_equals(Tool::Paint)),
+                erase.run_if(resource_equals(Tool::Erase)),
+                apply_cursor_force.run_if(resource_equals(Tool::Force)),
+                place_emmiters.run_if(resource_equals(Tool::Emmiter)),
+                bond_particles.run_if(resource_equals(Tool::Bond)),
+                draw_rects,
+                draw_tool_preview,
+                update_tool_text)
+            .chain());
+    }
+}
+
+// Left click uses the active tool. Every tool but `Select` leaves panning to
+// the right and middle mouse buttons.
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Select,
+    Paint,
+    Emmiter,
+    Forcefield,
+    Collider,
+    Erase,
+    Bond,
+    Force,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ForceMode {
+    Attract,
+    Repel,
+    Stir,
+}
+
+impl ForceMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ForceMode::Attract => "attract",
+            ForceMode::Repel => "repel",
+            ForceMode::Stir => "stir",
+        }
+    }
+
+    pub fn next(&self) -> ForceMode {
+        match self {
+            ForceMode::Attract => ForceMode::Repel,
+            ForceMode::Repel => ForceMode::Stir,
+            ForceMode::Stir => ForceMode::Attract,
+        }
+    }
+}
+
+impl Tool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Select => "Select",
+            Tool::Paint => "Paint",
+            Tool::Emmiter => "Emitter",
+            Tool::Forcefield => "Forcefield",
+            Tool::Collider => "Collider",
+            Tool::Erase => "Erase",
+            Tool::Force => "Force",
+            Tool::Bond => "Bond",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ToolSettings {
+    pub species: usize,
+    pub charge: f32,
+    pub brush_radius: f32,
+    pub force: ForceMode,
+    drag_start: Option<Vec2>,
+    bond_start: Option<Entity>,
+}
+
+// Forcefields and colliders drawn with the tools. Only these can be erased,
+// so the arena walls and arena wide fields stay.
+#[derive(Component)]
+struct Drawn;
+
+#[derive(Component)]
+struct ToolText;
+
+const BRUSH_RADIUS: f32 = 100.0;
+const MIN_BRUSH_RADIUS: f32 = 10.0;
+const MAX_BRUSH_RADIUS: f32 = 2000.0;
+const PAINT_RATE: f32 = 100.0;
+const EMMITER_PICK_RADIUS: f32 = 50.0;
+const BOND_PICK_RADIUS: f32 = 30.0;
+const MIN_RECT_SIZE: f32 = 10.0;
+const FORCEFIELD_STRENGTH: f32 = 500.0;
+const FORCE_STRENGTH: f32 = 2000.0;
+const STIR_COUPLING: f32 = 10.0;
+const PREVIEW_COLOUR: Color = Color::srgba(1.0, 1.0, 1.0, 0.5);
+
+fn spawn_tool_text(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Tool: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(230.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        ToolText
+    ));
+}
+
+// 1-8 pick a tool, Q cycles the painted charge, Z and X the species within
+// the interaction matrix, R the force mode and 9 and 0 change the brush radius.
+fn select_tool(
+    keys: Res<ButtonInput<KeyCode>>,
+    matrix: Res<InteractionMatrix>,
+    mut tool: ResMut<Tool>,
+    mut settings: ResMut<ToolSettings>,
+    mut q_camera: Query<&mut PanCam>,
+) {
+    let tools = [
+        (KeyCode::Digit1, Tool::Select),
+        (KeyCode::Digit2, Tool::Paint),
+        (KeyCode::Digit3, Tool::Emmiter),
+        (KeyCode::Digit4, Tool::Forcefield),
+        (KeyCode::Digit5, Tool::Collider),
+        (KeyCode::Digit6, Tool::Erase),
+        (KeyCode::Digit7, Tool::Bond),
+        (KeyCode::Digit8, Tool::Force),
+    ];
+    for (key, next) in tools {
+        if keys.just_pressed(key) && *tool != next {
+            *tool = next;
+            settings.drag_start = None;
+            settings.bond_start = None;
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyQ) {
+        settings.charge = if settings.charge > 0.0 { -1.0 } else if settings.charge < 0.0 { 0.0 } else { 1.0 };
+    }
+    if keys.just_pressed(KeyCode::KeyZ) {
+        settings.species = settings.species.saturating_sub(1);
+    }
+    if keys.just_pressed(KeyCode::KeyX) {
+        settings.species = (settings.species + 1).min(matrix.size.saturating_sub(1));
+    }
+    if keys.just_pressed(KeyCode::KeyR) {
+        settings.force = settings.force.next();
+    }
+    if keys.just_pressed(KeyCode::Digit9) {
+        settings.brush_radius = (settings.brush_radius / 2.0).max(MIN_BRUSH_RADIUS);
+    }
+    if keys.just_pressed(KeyCode::Digit0) {
+        settings.brush_radius = (settings.brush_radius * 2.0).min(MAX_BRUSH_RADIUS);
+    }
+
+    if tool.is_changed() {
+        for mut pancam in q_camera.iter_mut() {
+            pancam.grab_buttons = match *tool {
+                Tool::Select => vec![MouseButton::Left, MouseButton::Right, MouseButton::Middle],
+                _ => vec![MouseButton::Right, MouseButton::Middle],
+            };
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn paint_particles(
+    mut commands: Commands,
+    mut pending: Local<f32>,
+    time: Res<Time>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    settings: Res<ToolSettings>,
+    assets: Res<ParticleAssets>,
+    counts: Res<ParticleCounts>,
+) {
+    let (true, Some(center)) = (buttons.pressed(MouseButton::Left), cursor.0) else {
+        *pending = 0.0;
+        return;
+    };
+    *pending += PAINT_RATE * time.delta_seconds();
+    let count = (*pending as usize).min(MAX_PARTICLE_COUNT.saturating_sub(counts.total));
+    *pending = pending.fract();
+
+    for _i in 0..count {
+        // Uniform over the brush disc.
+        let offset = Vec2::from_angle(random::<f32>() * TAU) * settings.brush_radius * random::<f32>().sqrt();
+        spawn_particle(&mut commands, &assets, center + offset, Vec2::ZERO, settings.species, settings.charge);
+    }
+}
+
+// Removes particles and emitters within the brush and drawn forcefields and
+// colliders under the cursor.
+#[allow(clippy::too_many_arguments)]
+fn erase(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    settings: Res<ToolSettings>,
+    q_particles: Query<(Entity, &Transform), With<Particle>>,
+    q_emmiters: Query<(Entity, &Emmiter)>,
+    q_forcefields: Query<(Entity, &Forcefield), With<Drawn>>,
+    q_colliders: Query<(Entity, &Collider), With<Drawn>>,
+) {
+    let (true, Some(center)) = (buttons.pressed(MouseButton::Left), cursor.0) else {
+        return;
+    };
+    let inside = |position: Vec3| position.xy().distance(center) < settings.brush_radius;
+
+    for (entity, transform) in q_particles.iter() {
+        if inside(transform.translation) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, emmiter) in q_emmiters.iter() {
+        if inside(emmiter.transform.translation) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, forcefield) in q_forcefields.iter() {
+        if forcefield.rect.contains(center) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, collider) in q_colliders.iter() {
+        if collider.aabb.closest_point(center) == center {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Pulls particles within the brush towards the cursor, pushes them away or
+// drags them along with the cursor, falling off linearly to the brush edge.
+fn apply_cursor_force(
+    mut previous: Local<Option<Vec2>>,
+    time: Res<Time>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    settings: Res<ToolSettings>,
+    mut q: Query<(&mut Velocity, &Transform), With<Particle>>,
+) {
+    let dt = time.delta_seconds();
+    let (true, Some(center)) = (buttons.pressed(MouseButton::Left), cursor.0) else {
+        *previous = None;
+        return;
+    };
+    let cursor_velocity = match previous.replace(center) {
+        Some(last) if dt > 0.0 => (center - last) / dt,
+        _ => Vec2::ZERO,
+    };
+
+    let ToolSettings { brush_radius, force, .. } = *settings;
+    q.par_iter_mut().for_each(|(mut velocity, transform)| {
+        let delta = center - transform.translation.xy();
+        let falloff = 1.0 - delta.length() / brush_radius;
+        if falloff <= 0.0 {
+            return;
+        }
+        let change = match force {
+            ForceMode::Attract => delta.normalize_or_zero() * FORCE_STRENGTH * falloff * dt,
+            ForceMode::Repel => -delta.normalize_or_zero() * FORCE_STRENGTH * falloff * dt,
+            ForceMode::Stir => (cursor_velocity - velocity.0) * (STIR_COUPLING * falloff * dt).min(1.0),
+        };
+        velocity.0 += change;
+    });
+}
+
+// Clicking next to an emitter drags it along with the cursor, clicking
+// anywhere else places a new one. Emitters dropped onto a particle that they
+// didn't emit follow that particle around.
+fn place_emmiters(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    q: Query<(Entity, &Emmiter, Option<&EmmiterAttachment>)>,
+    q_particles: Query<(Entity, &Transform, Option<&EmittedBy>), With<Particle>>,
+) {
+    if buttons.just_released(MouseButton::Left) {
+        for (entity, _, attachment) in q.iter() {
+            if !matches!(attachment, Some(EmmiterAttachment::Cursor)) {
+                continue;
+            }
+            let target = cursor.0.and_then(|position| nearest_particle(
+                position,
+                EMMITER_PICK_RADIUS,
+                q_particles
+                    .iter()
+                    .filter(|(.., emitted_by)| emitted_by.is_none_or(|emitted_by| emitted_by.0 != entity))
+                    .map(|(particle, transform, _)| (particle, transform)),
+            ));
+            match target {
+                Some(particle) => commands.entity(entity).insert(EmmiterAttachment::Particle(particle)),
+                None => commands.entity(entity).remove::<EmmiterAttachment>(),
+            };
+        }
+    }
+
+    let (true, Some(position)) = (buttons.just_pressed(MouseButton::Left), cursor.0) else {
+        return;
+    };
+    let nearest = q
+        .iter()
+        .map(|(entity, emmiter, _)| (entity, emmiter.transform.translation.xy().distance(position)))
+        .filter(|(_, distance)| *distance < EMMITER_PICK_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    match nearest {
+        Some((entity, _)) => {
+            commands.entity(entity).insert(EmmiterAttachment::Cursor);
+        }
+        None => {
+            commands.spawn(Emmiter::new(position));
+        }
+    }
+}
+
+fn nearest_particle<'a>(
+    position: Vec2,
+    radius: f32,
+    particles: impl Iterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    particles
+        .map(|(entity, transform)| (entity, transform.translation.xy().distance(position)))
+        .filter(|(_, distance)| *distance < radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity)
+}
+
+// Dragging from one particle to another bonds them with the `BondSettings`.
+fn bond_particles(
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    mut settings: ResMut<ToolSettings>,
+    mut bonds: EventWriter<BondEvent>,
+    q: Query<(Entity, &Transform), With<Particle>>,
+) {
+    let Some(position) = cursor.0 else {
+        return;
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        settings.bond_start = nearest_particle(position, BOND_PICK_RADIUS, q.iter());
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    if let (Some(a), Some(b)) = (settings.bond_start.take(), nearest_particle(position, BOND_PICK_RADIUS, q.iter())) {
+        bonds.send(BondEvent { a, b });
+    }
+}
+
+// Dragging spans a rectangle. Forcefields push along the drag direction.
+fn draw_rects(
+    mut commands: Commands,
+    tool: Res<Tool>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorPosition>,
+    forcefield_assets: Res<ForcefieldAssets>,
+    collider_assets: Res<ColliderAssets>,
+    mut settings: ResMut<ToolSettings>,
+) {
+    if !matches!(*tool, Tool::Forcefield | Tool::Collider) {
+        return;
+    }
+    let Some(position) = cursor.0 else {
+        return;
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        settings.drag_start = Some(position);
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(start) = settings.drag_start.take() else {
+        return;
+    };
+
+    let rect = Rect::from_corners(start, position);
+    if rect.width() < MIN_RECT_SIZE || rect.height() < MIN_RECT_SIZE {
+        return;
+    }
+    let transform = Transform::from_translation(rect.center().extend(0.0))
+        .with_scale(rect.size().extend(1.0));
+    match *tool {
+        Tool::Forcefield => {
+            commands.spawn((
+                Forcefield {
+                    rect,
+                    kind: ForcefieldKind::Uniform((position - start).normalize() * FORCEFIELD_STRENGTH),
+                    modulation: Modulation::default()
+                },
+                MaterialMesh2dBundle {
+                    mesh: forcefield_assets.square.clone(),
+                    material: forcefield_assets.green.clone(),
+                    transform,
+                    ..default()
+                },
+                Drawn
+            ));
+        }
+        Tool::Collider => {
+            commands.spawn((
+                Collider {
+                    aabb: Aabb2d {
+                        min: rect.min,
+                        max: rect.max
+                    }
+                },
+                MaterialMesh2dBundle {
+                    mesh: collider_assets.square.clone(),
+                    material: collider_assets.white.clone(),
+                    transform,
+                    ..default()
+                },
+                Drawn
+            ));
+        }
+        _ => {}
+    }
+}
+
+fn draw_tool_preview(
+    mut gizmos: Gizmos,
+    tool: Res<Tool>,
+    settings: Res<ToolSettings>,
+    cursor: Res<CursorPosition>,
+    q_emmiters: Query<&Emmiter>,
+    q_particles: Query<&Transform, With<Particle>>,
+) {
+    let Some(position) = cursor.0 else {
+        return;
+    };
+    match *tool {
+        Tool::Select => {}
+        Tool::Paint | Tool::Erase | Tool::Force => {
+            gizmos.circle_2d(position, settings.brush_radius, PREVIEW_COLOUR);
+        }
+        Tool::Emmiter => {
+            for emmiter in q_emmiters.iter() {
+                gizmos.circle_2d(emmiter.transform.translation.xy(), EMMITER_PICK_RADIUS, PREVIEW_COLOUR);
+            }
+        }
+        Tool::Forcefield | Tool::Collider => {
+            if let Some(start) = settings.drag_start {
+                let rect = Rect::from_corners(start, position);
+                gizmos.rect_2d(rect.center(), 0.0, rect.size(), PREVIEW_COLOUR);
+            }
+        }
+        Tool::Bond => {
+            gizmos.circle_2d(position, BOND_PICK_RADIUS, PREVIEW_COLOUR);
+            if let Some(transform) = settings.bond_start.and_then(|start| q_particles.get(start).ok()) {
+                gizmos.line_2d(transform.translation.xy(), position, PREVIEW_COLOUR);
+            }
+        }
+    }
+}
+
+fn update_tool_text(
+    tool: Res<Tool>,
+    settings: Res<ToolSettings>,
+    matrix: Res<InteractionMatrix>,
+    mut q: Query<&mut Text, With<ToolText>>,
+) {
+    let mut text = q.single_mut();
+    let name = tool.name();
+    let ToolSettings { species, charge, brush_radius, .. } = *settings;
+    let max_species = matrix.size.saturating_sub(1);
+    text.sections[1].value = match *tool {
+        Tool::Select => format!("{name} (1-8 to switch)"),
+        Tool::Paint => format!("{name} (charge {charge:+.0}, species {species} of 0-{max_species}, brush {brush_radius:.0})"),
+        Tool::Erase => format!("{name} (brush {brush_radius:.0})"),
+        Tool::Force => format!("{name} ({}, R to cycle, brush {brush_radius:.0})", settings.force.name()),
+        Tool::Emmiter => format!("{name} (drag onto a particle to attach)"),
+        Tool::Bond => format!("{name} (drag between particles)"),
+        _ => name.to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world(center: Vec2) -> World {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(CursorPosition(Some(center)));
+        world.insert_resource(ParticleAssets::placeholder());
+        world.init_resource::<ParticleCounts>();
+        world.insert_resource(ToolSettings {
+            species: 2,
+            charge: -1.0,
+            brush_radius: BRUSH_RADIUS,
+            force: ForceMode::Attract,
+            drag_start: None,
+            bond_start: None,
+        });
+        let mut buttons = ButtonInput::<MouseButton>::default();
+        buttons.press(MouseButton::Left);
+        world.insert_resource(buttons);
+        world
+    }
+
+    fn particles(world: &mut World) -> Vec<(Vec2, usize, f32)> {
+        world
+            .query::<(&Transform, &Species, &Charge)>()
+            .iter(world)
+            .map(|(transform, species, charge)| (transform.translation.xy(), species.0, charge.0))
+            .collect()
+    }
+
+    #[test]
+    fn painting_spawns_at_the_paint_rate() {
+        let center = Vec2::new(300.0, -200.0);
+        let mut world = world(center);
+        let paint = world.register_system(paint_particles);
+
+        // 100 particles a second, with the remainder carried over.
+        for _frame in 0..3 {
+            world.resource_mut::<Time>().advance_by(Duration::from_millis(15));
+            world.run_system(paint).unwrap();
+        }
+        let painted = particles(&mut world);
+        assert_eq!(painted.len(), 4);
+        assert!(painted.iter().all(|(position, species, charge)| {
+            position.distance(center) <= BRUSH_RADIUS && *species == 2 && *charge == -1.0
+        }));
+
+        // Never past the particle cap.
+        world.resource_mut::<ParticleCounts>().total = MAX_PARTICLE_COUNT - 1;
+        world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        world.run_system(paint).unwrap();
+        assert_eq!(particles(&mut world).len(), 5);
+    }
+
+    #[test]
+    fn erasing_removes_what_is_under_the_brush() {
+        let mut world = world(Vec2::ZERO);
+        for x in [0.0, 90.0, 110.0, -300.0] {
+            spawn_test_particle(&mut world, Vec2::new(x, 0.0), Vec2::ZERO, 0, 1.0);
+        }
+        world.spawn(Emmiter::new(Vec2::new(0.0, 50.0)));
+        world.spawn(Emmiter::new(Vec2::new(0.0, 150.0)));
+        let forcefield = || Forcefield {
+            rect: Rect::new(-10.0, -10.0, 10.0, 10.0),
+            kind: ForcefieldKind::Uniform(Vec2::X),
+            modulation: Modulation::default(),
+        };
+        world.spawn((forcefield(), Drawn));
+        world.spawn(forcefield());
+        world.spawn((Collider { aabb: Aabb2d::new(Vec2::ZERO, Vec2::splat(10.0)) }, Drawn));
+
+        world.run_system_once(erase);
+        let left = particles(&mut world).into_iter().map(|(position, ..)| position.x).collect::<Vec<_>>();
+        assert_eq!(left.len(), 2);
+        assert!(left.contains(&110.0) && left.contains(&-300.0));
+        assert_eq!(world.query::<&Emmiter>().iter(&world).count(), 1);
+        assert_eq!(world.query::<&Forcefield>().iter(&world).count(), 1);
+        assert_eq!(world.query::<&Collider>().iter(&world).count(), 0);
+    }
+
+    fn push(force: ForceMode, distances: &[f32], frames: usize) -> Vec<Vec2> {
+        let mut world = world(Vec2::ZERO);
+        world.resource_mut::<ToolSettings>().force = force;
+        let particles = distances
+            .iter()
+            .map(|x| spawn_test_particle(&mut world, Vec2::new(*x, 0.0), Vec2::ZERO, 0, 1.0))
+            .collect::<Vec<_>>();
+        let apply = world.register_system(apply_cursor_force);
+        for frame in 0..frames {
+            world.insert_resource(CursorPosition(Some(Vec2::new(0.0, frame as f32))));
+            world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+            world.run_system(apply).unwrap();
+        }
+        particles.into_iter().map(|entity| world.get::<Velocity>(entity).unwrap().0).collect()
+    }
+
+    #[test]
+    fn cursor_forces_fall_off_to_the_brush_edge() {
+        let distances = [25.0, 50.0, -50.0, 150.0];
+        let dt = 0.1;
+        let attract = push(ForceMode::Attract, &distances, 1);
+        assert!(attract[0].distance(Vec2::new(-0.75 * FORCE_STRENGTH * dt, 0.0)) < 1e-2);
+        assert!(attract[1].distance(Vec2::new(-0.5 * FORCE_STRENGTH * dt, 0.0)) < 1e-2);
+        assert!(attract[2].distance(Vec2::new(0.5 * FORCE_STRENGTH * dt, 0.0)) < 1e-2);
+        assert_eq!(attract[3], Vec2::ZERO);
+
+        let repel = push(ForceMode::Repel, &distances, 1);
+        assert!(repel.iter().zip(&attract).all(|(repel, attract)| *repel == -*attract));
+    }
+
+    #[test]
+    fn stirring_drags_particles_along_with_the_cursor() {
+        // The cursor moves up 10 units a second. On the first frame it has
+        // no velocity yet, so nothing moves.
+        let distances = [0.0, 50.0, 150.0];
+        assert!(push(ForceMode::Stir, &distances, 1).iter().all(|velocity| *velocity == Vec2::ZERO));
+
+        let dt = 0.1;
+        let stirred = |x: f32| {
+            let falloff = 1.0 - Vec2::new(x, 1.0).length() / BRUSH_RADIUS;
+            Vec2::new(0.0, 10.0 * (STIR_COUPLING * falloff * dt).min(1.0))
+        };
+        let stir = push(ForceMode::Stir, &distances, 2);
+        assert!(stir[0].distance(stirred(0.0)) < 1e-3);
+        assert!(stir[1].distance(stirred(50.0)) < 1e-3);
+        assert!(stir[1].length() < stir[0].length());
+        assert_eq!(stir[2], Vec2::ZERO);
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap,
+};
+
+use std::collections::VecDeque;
+
+use crate::particle::*;
+
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TrailSettings {
+            filter: TrailFilter::Off,
+            length: TRAIL_LENGTH,
+            fade: TRAIL_FADE,
+        });
+        app.add_systems(Update, (update_trail_filter, record_trails, draw_trails).chain());
+    }
+}
+
+// Which particles get a trail. `Marked` only picks particles carrying
+// `ShowTrail`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrailFilter {
+    Off,
+    All,
+    Species(usize),
+    Marked,
+}
+
+// Trails keep the last `length` positions. Alpha falls off towards the tail
+// as (i / length)^fade, so 0 disables fading.
+#[derive(Resource)]
+pub struct TrailSettings {
+    pub filter: TrailFilter,
+    pub length: usize,
+    pub fade: f32,
+}
+
+#[derive(Component)]
+pub struct ShowTrail;
+
+#[derive(Component, Default)]
+pub struct Trail {
+    points: VecDeque<Vec2>,
+}
+
+const TRAIL_LENGTH: usize = 40;
+const TRAIL_FADE: f32 = 1.5;
+const MAX_TRAIL_STEP: f32 = 200.0;
+
+#[allow(clippy::type_complexity)]
+fn update_trail_filter(
+    mut commands: Commands,
+    settings: Res<TrailSettings>,
+    q: Query<(Entity, &Species, Has<ShowTrail>, Has<Trail>), With<Particle>>,
+) {
+    for (entity, species, marked, has_trail) in q.iter() {
+        let wanted = match settings.filter {
+            TrailFilter::Off => false,
+            TrailFilter::All => true,
+            TrailFilter::Species(s) => species.0 == s,
+            TrailFilter::Marked => marked,
+        };
+        if wanted && !has_trail {
+            commands.entity(entity).try_insert(Trail::default());
+        } else if !wanted && has_trail {
+            commands.entity(entity).remove::<Trail>();
+        }
+    }
+}
+
+fn record_trails(
+    settings: Res<TrailSettings>,
+    mut q: Query<(&Transform, &mut Trail)>,
+) {
+    q.par_iter_mut().for_each(|(transform, mut trail)| {
+        let position = transform.translation.xy();
+        // Teleported particles start a new trail.
+        if trail.points.back().is_some_and(|last| last.distance(position) > MAX_TRAIL_STEP) {
+            trail.points.clear();
+        }
+        while trail.points.len() >= settings.length.max(1) {
+            trail.points.pop_front();
+        }
+        trail.points.push_back(position);
+    });
+}
+
+fn draw_trails(
+    mut gizmos: Gizmos,
+    settings: Res<TrailSettings>,
+    materials: Res<Assets<ColorMaterial>>,
+    q: Query<(&Trail, &ParticleColour)>,
+) {
+    let mut colours = HashMap::new();
+    for (trail, ParticleColour(material)) in q.iter() {
+        let colour = *colours
+            .entry(material.id())
+            .or_insert_with(|| materials.get(material).map_or(Color::WHITE, |material| material.color));
+        let n = trail.points.len();
+        gizmos.linestrip_gradient_2d(trail.points.iter().enumerate().map(|(i, point)| {
+            let alpha = ((i + 1) as f32 / n as f32).powf(settings.fade);
+            (*point, colour.with_alpha(colour.alpha() * alpha))
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        tasks::{ComputeTaskPool, TaskPool},
+    };
+
+    use super::*;
+
+    fn settings(filter: TrailFilter, length: usize) -> TrailSettings {
+        TrailSettings { filter, length, fade: TRAIL_FADE }
+    }
+
+    #[test]
+    fn trails_keep_the_last_positions() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut world = World::new();
+        world.insert_resource(settings(TrailFilter::All, 3));
+        let entity = spawn_test_particle(&mut world, Vec2::ZERO, Vec2::ZERO, 0, 1.0);
+        world.run_system_once(update_trail_filter);
+
+        for x in 0..5 {
+            world.get_mut::<Transform>(entity).unwrap().translation.x = x as f32 * 10.0;
+            world.run_system_once(record_trails);
+        }
+        let points = &world.get::<Trail>(entity).unwrap().points;
+        assert_eq!(points.iter().map(|point| point.x).collect::<Vec<_>>(), [20.0, 30.0, 40.0]);
+
+        // A jump further than a frame could move starts over.
+        world.get_mut::<Transform>(entity).unwrap().translation.x = 1000.0;
+        world.run_system_once(record_trails);
+        let points = &world.get::<Trail>(entity).unwrap().points;
+        assert_eq!(points.iter().map(|point| point.x).collect::<Vec<_>>(), [1000.0]);
+    }
+
+    #[test]
+    fn trails_follow_the_filter() {
+        let mut world = World::new();
+        world.insert_resource(settings(TrailFilter::All, TRAIL_LENGTH));
+        let first = spawn_test_particle(&mut world, Vec2::ZERO, Vec2::ZERO, 0, 1.0);
+        let second = spawn_test_particle(&mut world, Vec2::ZERO, Vec2::ZERO, 1, 1.0);
+        world.entity_mut(second).insert(ShowTrail);
+
+        let trails = |world: &mut World, filter| {
+            world.resource_mut::<TrailSettings>().filter = filter;
+            world.run_system_once(update_trail_filter);
+            [first, second].map(|entity| world.get::<Trail>(entity).is_some())
+        };
+        assert_eq!(trails(&mut world, TrailFilter::All), [true, true]);
+        assert_eq!(trails(&mut world, TrailFilter::Species(0)), [true, false]);
+        assert_eq!(trails(&mut world, TrailFilter::Marked), [false, true]);
+        assert_eq!(trails(&mut world, TrailFilter::Off), [false, false]);
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::{
     prelude::*,
     dev_tools::fps_overlay::FpsOverlayPlugin,
+    window::PrimaryWindow,
 };
 
 use bevy_pancam:: {
@@ -9,27 +10,83 @@ use bevy_pancam:: {
 };
 
 use crate:: {
-    particle::ParticleCounter,
+    particle::{ParticleCounts, AnnihilationRate},
     physics::TotalKineticEnergy,
+    sink::SinkCounters,
+    thermostat::{Thermostat, temperature},
+    forcefield::ArenaNoise,
+    display::{DensityGrid, VelocityGrid, VelocityDisplay, ColourMode, ColourQuantity},
+    trail::{TrailSettings, TrailFilter},
+    render::ParticleRenderMode,
+    inspect::InspectPlugin,
+    tools::ToolsPlugin,
+    panel::ParameterPanelPlugin,
+    matrix::MatrixEditorPlugin,
 };
 
 pub struct UIPlugin;
 
+const MIN_DENSITY_RESOLUTION: u32 = 8;
+const MAX_DENSITY_RESOLUTION: u32 = 512;
+const MIN_VELOCITY_RESOLUTION: u32 = 4;
+const MAX_VELOCITY_RESOLUTION: u32 = 80;
+const LEGEND_SWATCHES: usize = 16;
+const MIN_TRAIL_LENGTH: usize = 5;
+const MAX_TRAIL_LENGTH: usize = 640;
+
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(PanCamPlugin);
         app.add_plugins(FpsOverlayPlugin::default());
+        app.add_plugins(InspectPlugin);
+        app.add_plugins(ToolsPlugin);
+        app.add_plugins(ParameterPanelPlugin);
+        app.add_plugins(MatrixEditorPlugin);
+        app.insert_resource(CursorPosition(None));
         app.add_systems(Startup, (setup_ui, setup_camera));
-        app.add_systems(Update, (update_counter, update_kinetic_energy));
+        app.add_systems(PreUpdate, update_cursor_position);
+        app.add_systems(Update, (update_counter, update_kinetic_energy, update_sink_text, update_annihilation_text));
+        app.add_systems(Update, (cycle_thermostat, update_thermostat_text).chain());
+        app.add_systems(Update, (cycle_arena_noise, update_noise_text).chain());
+        app.add_systems(Update, (adjust_density_overlay, adjust_velocity_grid, adjust_trails, toggle_render_mode));
+        app.add_systems(Update, (cycle_colour_mode, update_colour_legend).chain());
     }
 }
 
+// Cursor position in world space, `None` while it is outside the window.
+#[derive(Resource)]
+pub struct CursorPosition(pub Option<Vec2>);
+
 #[derive(Component)]
 struct CounterText;
 
 #[derive(Component)]
 struct KineticEnergyText;
 
+#[derive(Component)]
+struct SinkText;
+
+#[derive(Component)]
+struct AnnihilationText;
+
+#[derive(Component)]
+struct ThermostatText;
+
+#[derive(Component)]
+struct NoiseText;
+
+#[derive(Component)]
+struct LegendText;
+
+#[derive(Component)]
+struct LegendBar;
+
+#[derive(Component)]
+struct LegendSwatch(usize);
+
+#[derive(Component)]
+struct LegendRange;
+
 fn setup_ui(
     mut commands: Commands
 ) {
@@ -89,15 +146,194 @@ fn setup_ui(
         }),
         KineticEnergyText
     ));
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Absorbed: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(95.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        SinkText
+    ));
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Annihilations: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::new(
+                "/s",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(140.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        AnnihilationText
+    ));
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Temperature: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(185.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        ThermostatText
+    ));
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Noise: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(275.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        NoiseText
+    ));
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Colour: ",
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font_size: 40.0,
+                    ..default()
+                }
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(95.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+        LegendText
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(65.0),
+                    right: Val::Px(15.0),
+                    height: Val::Px(25.0),
+                    ..default()
+                },
+                ..default()
+            },
+            LegendBar
+        ))
+        .with_children(|parent| {
+            for i in 0..LEGEND_SWATCHES {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(20.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    LegendSwatch(i)
+                ));
+            }
+        });
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                ..default()
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(25.0),
+            right: Val::Px(15.0),
+            ..default()
+        }),
+        LegendRange
+    ));
 }
 
 fn update_counter(
-    counter: Res<ParticleCounter>,
+    counts: Res<ParticleCounts>,
     mut q: Query<&mut Text, With<CounterText>>,
 ) {
     let mut text = q.single_mut();
-    let count = counter.0;
-    text.sections[1].value = format!("{count}");
+    let ParticleCounts { total, positive, negative, .. } = *counts;
+    text.sections[1].value = format!("{total} (+{positive} / -{negative})");
 }
 
 fn update_kinetic_energy(
@@ -109,6 +345,201 @@ fn update_kinetic_energy(
     text.sections[1].value = format!("{value:.2}");
 }
 
+fn update_sink_text(
+    counters: Res<SinkCounters>,
+    mut q: Query<&mut Text, With<SinkText>>,
+) {
+    let mut text = q.single_mut();
+    let total: u64 = counters.0.values().map(|count| count.total).sum();
+    let rate: f32 = counters.0.values().map(|count| count.rate.per_second).sum();
+    text.sections[1].value = format!("{total} ({rate:.1}/s)");
+}
+
+fn update_annihilation_text(
+    rate: Res<AnnihilationRate>,
+    mut q: Query<&mut Text, With<AnnihilationText>>,
+) {
+    let mut text = q.single_mut();
+    let value = rate.0.per_second;
+    text.sections[1].value = format!("{value:.1}");
+}
+
+fn cycle_thermostat(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut thermostat: ResMut<Thermostat>,
+) {
+    if keys.just_pressed(KeyCode::KeyT) {
+        *thermostat = thermostat.next();
+    }
+}
+
+fn update_thermostat_text(
+    energy: Res<TotalKineticEnergy>,
+    counts: Res<ParticleCounts>,
+    thermostat: Res<Thermostat>,
+    mut q: Query<&mut Text, With<ThermostatText>>,
+) {
+    let mut text = q.single_mut();
+    let value = temperature(&energy, counts.total);
+    let name = thermostat.name();
+    text.sections[1].value = match thermostat.target() {
+        Some(target) => format!("{value:.0} ({name}, target {target:.0})"),
+        None => format!("{value:.0} (thermostat {name}, T to cycle)"),
+    };
+}
+
+fn cycle_arena_noise(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut arena_noise: ResMut<ArenaNoise>,
+) {
+    if keys.just_pressed(KeyCode::KeyN) {
+        *arena_noise = arena_noise.next();
+    }
+}
+
+fn update_noise_text(
+    arena_noise: Res<ArenaNoise>,
+    mut q: Query<&mut Text, With<NoiseText>>,
+) {
+    if arena_noise.is_changed() {
+        q.single_mut().sections[1].value = format!("{} (N to cycle)", arena_noise.name());
+    }
+}
+
+// H toggles the overlay, [ and ] change its resolution, - and = its colour
+// scale and M switches colour maps.
+fn adjust_density_overlay(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut grid: ResMut<DensityGrid>,
+) {
+    if keys.just_pressed(KeyCode::KeyH) {
+        grid.visible = !grid.visible;
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        grid.resolution = (grid.resolution / 2).max(MIN_DENSITY_RESOLUTION);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        grid.resolution = (grid.resolution * 2).min(MAX_DENSITY_RESOLUTION);
+    }
+    if keys.just_pressed(KeyCode::Minus) {
+        grid.max_density = (grid.max_density / 2.0).max(1.0);
+    }
+    if keys.just_pressed(KeyCode::Equal) {
+        grid.max_density *= 2.0;
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        grid.colour_map = grid.colour_map.next();
+    }
+}
+
+// V cycles between arrows, streamlines and nothing, , and . change the
+// probe grid resolution.
+fn adjust_velocity_grid(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut grid: ResMut<VelocityGrid>,
+) {
+    if keys.just_pressed(KeyCode::KeyV) {
+        grid.display = match grid.display {
+            VelocityDisplay::Off => VelocityDisplay::Arrows,
+            VelocityDisplay::Arrows => VelocityDisplay::Streamlines,
+            VelocityDisplay::Streamlines => VelocityDisplay::Off,
+        };
+    }
+    if keys.just_pressed(KeyCode::Comma) {
+        grid.resolution = (grid.resolution / 2).max(MIN_VELOCITY_RESOLUTION);
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        grid.resolution = (grid.resolution * 2).min(MAX_VELOCITY_RESOLUTION);
+    }
+}
+
+// L cycles trails off, on for every particle, for marked particles and for
+// each species in turn, ; and ' change their length.
+fn adjust_trails(
+    keys: Res<ButtonInput<KeyCode>>,
+    counts: Res<ParticleCounts>,
+    mut settings: ResMut<TrailSettings>,
+) {
+    if keys.just_pressed(KeyCode::KeyL) {
+        let species = counts.species.keys().copied().max().unwrap_or(0);
+        settings.filter = match settings.filter {
+            TrailFilter::Off => TrailFilter::All,
+            TrailFilter::All => TrailFilter::Marked,
+            TrailFilter::Marked => TrailFilter::Species(0),
+            TrailFilter::Species(s) if s < species => TrailFilter::Species(s + 1),
+            TrailFilter::Species(_) => TrailFilter::Off,
+        };
+    }
+    if keys.just_pressed(KeyCode::Semicolon) {
+        settings.length = (settings.length / 2).max(MIN_TRAIL_LENGTH);
+    }
+    if keys.just_pressed(KeyCode::Quote) {
+        settings.length = (settings.length * 2).min(MAX_TRAIL_LENGTH);
+    }
+}
+
+fn toggle_render_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mode: ResMut<ParticleRenderMode>,
+) {
+    if keys.just_pressed(KeyCode::KeyI) {
+        *mode = match *mode {
+            ParticleRenderMode::Meshes => ParticleRenderMode::Instanced,
+            ParticleRenderMode::Instanced => ParticleRenderMode::Meshes,
+        };
+    }
+}
+
+// C cycles the coloured quantity, shift+C the colour map.
+fn cycle_colour_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mode: ResMut<ColourMode>,
+) {
+    if !keys.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        mode.map = mode.map.next();
+    } else {
+        mode.quantity = mode.quantity.next();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_colour_legend(
+    mode: Res<ColourMode>,
+    mut q_text: Query<&mut Text, (With<LegendText>, Without<LegendRange>)>,
+    mut q_range: Query<&mut Text, (With<LegendRange>, Without<LegendText>)>,
+    mut q_bar: Query<&mut Visibility, With<LegendBar>>,
+    mut q_swatches: Query<(&LegendSwatch, &mut BackgroundColor)>,
+) {
+    let mut text = q_text.single_mut();
+    let mut range = q_range.single_mut();
+    let mut bar = q_bar.single_mut();
+    let name = mode.quantity.name();
+
+    let continuous = !matches!(mode.quantity, ColourQuantity::Species | ColourQuantity::Cluster);
+    *bar = if mode.quantity == ColourQuantity::Species { Visibility::Hidden } else { Visibility::Inherited };
+    text.sections[1].value = if mode.quantity == ColourQuantity::Species {
+        format!("{name} (C to cycle)")
+    } else {
+        format!("{name} ({})", mode.map.name())
+    };
+    range.sections[0].value = if continuous {
+        format!("{:.1} .. {:.1}", mode.min, mode.max)
+    } else {
+        String::new()
+    };
+
+    if !mode.is_changed() {
+        return;
+    }
+    for (swatch, mut colour) in q_swatches.iter_mut() {
+        let t = swatch.0 as f32 / (LEGEND_SWATCHES - 1) as f32;
+        colour.0 = mode.map.sample(t);
+    }
+}
+
 fn setup_camera(
     mut commands: Commands,
 ) {
@@ -124,3 +555,16 @@ fn setup_camera(
         PanCam::default()
     ));
 }
+
+pub(crate) fn update_cursor_position(
+    mut cursor: ResMut<CursorPosition>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (q_window.get_single(), q_camera.get_single()) else {
+        return;
+    };
+    cursor.0 = window
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position));
+}
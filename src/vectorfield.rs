@@ -0,0 +1,249 @@
+use bevy::{
+    prelude::*,
+    asset::{
+        AssetLoader,
+        LoadContext,
+        io::Reader,
+        AsyncReadExt,
+    },
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::TextureFormat,
+        texture::{
+            CompressedImageFormats,
+            ImageSampler,
+            ImageType,
+            TextureError,
+        },
+    },
+};
+
+use std::fmt;
+
+pub struct VectorFieldPlugin;
+
+impl Plugin for VectorFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<VectorField>();
+        app.init_asset_loader::<VectorFieldLoader>();
+    }
+}
+
+// A grid of force vectors, stored row by row starting from the bottom so
+// that `sample` can take coordinates in world orientation.
+#[derive(Asset, TypePath)]
+pub struct VectorField {
+    width: usize,
+    height: usize,
+    vectors: Vec<Vec2>,
+}
+
+impl VectorField {
+    fn get(&self, x: usize, y: usize) -> Vec2 {
+        self.vectors[y * self.width + x]
+    }
+
+    // Bilinear sample at `uv` in [0, 1]², clamped to the edges of the grid.
+    // The loaders never produce an empty grid, but one samples as zero.
+    pub fn sample(&self, uv: Vec2) -> Vec2 {
+        if self.vectors.is_empty() {
+            return Vec2::ZERO;
+        }
+        let max = Vec2::new((self.width - 1) as f32, (self.height - 1) as f32);
+        let p = (uv.clamp(Vec2::ZERO, Vec2::ONE)) * max;
+        let x0 = p.x.floor() as usize;
+        let y0 = p.y.floor() as usize;
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+        let f = p - p.floor();
+
+        let bottom = self.get(x0, y0).lerp(self.get(x1, y0), f.x);
+        let top = self.get(x0, y1).lerp(self.get(x1, y1), f.x);
+        bottom.lerp(top, f.y)
+    }
+}
+
+#[derive(Default)]
+struct VectorFieldLoader;
+
+#[derive(Debug)]
+enum VectorFieldLoaderError {
+    Io(std::io::Error),
+    Texture(TextureError),
+    Parse(String),
+}
+
+impl fmt::Display for VectorFieldLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VectorFieldLoaderError::Io(err) => write!(f, "could not read vector field: {err}"),
+            VectorFieldLoaderError::Texture(err) => write!(f, "could not decode vector field image: {err}"),
+            VectorFieldLoaderError::Parse(msg) => write!(f, "invalid vector field: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for VectorFieldLoaderError {}
+
+impl AssetLoader for VectorFieldLoader {
+    type Asset = VectorField;
+    type Settings = ();
+    type Error = VectorFieldLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<VectorField, VectorFieldLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(VectorFieldLoaderError::Io)?;
+        let is_image = load_context
+            .path()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        if is_image {
+            parse_image(&bytes)
+        } else {
+            parse_csv(&bytes)
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["field.csv", "field.png"]
+    }
+}
+
+// Each line is one row of the grid, top row first, holding `x,y` pairs:
+// `x0,y0,x1,y1,...`. Empty lines and lines starting with `#` are skipped.
+fn parse_csv(bytes: &[u8]) -> Result<VectorField, VectorFieldLoaderError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|err| VectorFieldLoaderError::Parse(err.to_string()))?;
+
+    let mut rows = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values = line
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| VectorFieldLoaderError::Parse(err.to_string()))?;
+        if values.iter().any(|value| !value.is_finite()) {
+            return Err(VectorFieldLoaderError::Parse(
+                format!("row {} has a non-finite component", rows.len())));
+        }
+        if values.len() % 2 != 0 {
+            return Err(VectorFieldLoaderError::Parse(
+                format!("row {} has an odd number of components", rows.len())));
+        }
+        rows.push(values.chunks(2).map(|v| Vec2::new(v[0], v[1])).collect::<Vec<_>>());
+    }
+
+    let width = rows.first().map_or(0, Vec::len);
+    if width == 0 || rows.iter().any(|row| row.len() != width) {
+        return Err(VectorFieldLoaderError::Parse("rows must be non-empty and of equal length".into()));
+    }
+
+    Ok(VectorField {
+        width,
+        height: rows.len(),
+        vectors: rows.into_iter().rev().flatten().collect(),
+    })
+}
+
+// The red and green channels map [0, 255] onto [-1, 1] for x and y.
+fn parse_image(bytes: &[u8]) -> Result<VectorField, VectorFieldLoaderError> {
+    let image = Image::from_buffer(
+        bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        false,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    ).map_err(VectorFieldLoaderError::Texture)?;
+    let image = image
+        .convert(TextureFormat::Rgba8Unorm)
+        .ok_or_else(|| VectorFieldLoaderError::Parse("unsupported pixel format".into()))?;
+
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    if width == 0 || height == 0 {
+        return Err(VectorFieldLoaderError::Parse("image is empty".into()));
+    }
+    let channel = |value: u8| value as f32 / 127.5 - 1.0;
+    let mut vectors = Vec::with_capacity(width * height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let pixel = &image.data[(y * width + x) * 4..];
+            vectors.push(Vec2::new(channel(pixel[0]), channel(pixel[1])));
+        }
+    }
+
+    Ok(VectorField { width, height, vectors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Top row (0, 1), (1, 1), bottom row (0, 0), (1, 0) as x,y vectors.
+    const GRID: &str = "\
+        # 2x2 grid\n\
+        \n\
+        0,1, 1,1\n\
+        0,0, 1,0\n";
+
+    #[test]
+    fn csv_skips_comments_and_flips_rows() {
+        let field = parse_csv(GRID.as_bytes()).unwrap();
+        assert_eq!((field.width, field.height), (2, 2));
+        assert_eq!(field.get(0, 0), Vec2::new(0.0, 0.0));
+        assert_eq!(field.get(1, 0), Vec2::new(1.0, 0.0));
+        assert_eq!(field.get(0, 1), Vec2::new(0.0, 1.0));
+
+        let vortex = parse_csv(include_bytes!("../assets/fields/vortex.field.csv")).unwrap();
+        assert_eq!((vortex.width, vortex.height), (9, 9));
+    }
+
+    #[test]
+    fn csv_rejects_malformed_rows() {
+        assert!(parse_csv(b"0,0,1,1\n0,0\n").is_err());
+        assert!(parse_csv(b"0,0,1\n").is_err());
+        assert!(parse_csv(b"0,zero\n").is_err());
+        assert!(parse_csv(b"# only a comment\n").is_err());
+        assert!(parse_csv(b"").is_err());
+        assert!(parse_csv(b"0,NaN\n").is_err());
+        assert!(parse_csv(b"inf,0\n").is_err());
+        assert!(parse_csv(b"0,0, 1,-infinity\n").is_err());
+    }
+
+    #[test]
+    fn sampling_is_bilinear() {
+        let field = parse_csv(GRID.as_bytes()).unwrap();
+        // Corners hit the grid values exactly.
+        assert_eq!(field.sample(Vec2::new(0.0, 0.0)), Vec2::new(0.0, 0.0));
+        assert_eq!(field.sample(Vec2::new(1.0, 0.0)), Vec2::new(1.0, 0.0));
+        assert_eq!(field.sample(Vec2::new(0.0, 1.0)), Vec2::new(0.0, 1.0));
+        assert_eq!(field.sample(Vec2::new(1.0, 1.0)), Vec2::new(1.0, 1.0));
+        // Edge midpoints and the centre blend their neighbours.
+        assert_eq!(field.sample(Vec2::new(0.5, 0.0)), Vec2::new(0.5, 0.0));
+        assert_eq!(field.sample(Vec2::new(0.0, 0.5)), Vec2::new(0.0, 0.5));
+        assert_eq!(field.sample(Vec2::new(0.5, 0.5)), Vec2::new(0.5, 0.5));
+        assert_eq!(field.sample(Vec2::new(0.25, 0.75)), Vec2::new(0.25, 0.75));
+        // Outside the unit square the edges are held.
+        assert_eq!(field.sample(Vec2::new(-1.0, 2.0)), Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn degenerate_fields_sample_safely() {
+        let single = parse_csv(b"3,4\n").unwrap();
+        assert_eq!(single.sample(Vec2::new(0.3, 0.9)), Vec2::new(3.0, 4.0));
+        let row = parse_csv(b"0,0, 2,0\n").unwrap();
+        assert_eq!(row.sample(Vec2::new(0.5, 0.5)), Vec2::new(1.0, 0.0));
+
+        let empty = VectorField { width: 0, height: 0, vectors: Vec::new() };
+        assert_eq!(empty.sample(Vec2::splat(0.5)), Vec2::ZERO);
+    }
+}